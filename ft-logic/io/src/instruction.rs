@@ -1,12 +1,13 @@
 use crate::H256;
-use ft_storage_io::{FTStorageAction, FTStorageEvent};
+use ft_storage_io::{FTStorageAction, FTStorageEvent, FTokenError};
 use gstd::{msg, prelude::*, ActorId};
 
 #[derive(Debug, Encode, Decode, TypeInfo, Clone)]
 pub enum InstructionState {
     ScheduledRun,
     ScheduledAbort,
-    RunWithError,
+    /// The storage has rejected the transaction with the error.
+    RunWithError(FTokenError),
     Finished,
 }
 
@@ -33,12 +34,12 @@ impl Instruction {
         }
     }
 
-    pub async fn start(&mut self) -> Result<(), ()> {
+    pub async fn start(&mut self) -> Result<(), FTokenError> {
         match self.state {
             InstructionState::ScheduledRun => {
                 let result = msg::send_for_reply_as::<_, FTStorageEvent>(
                     self.address,
-                    self.transaction,
+                    self.transaction.clone(),
                     0,
                     0,
                 )
@@ -49,13 +50,17 @@ impl Instruction {
                         self.state = InstructionState::ScheduledAbort;
                         Ok(())
                     }
+                    Ok(FTStorageEvent::Err(error)) => {
+                        self.state = InstructionState::RunWithError(error);
+                        Err(error)
+                    }
                     _ => {
-                        self.state = InstructionState::RunWithError;
-                        Err(())
+                        self.state = InstructionState::RunWithError(FTokenError::MessageFailed);
+                        Err(FTokenError::MessageFailed)
                     }
                 }
             }
-            InstructionState::RunWithError => Err(()),
+            InstructionState::RunWithError(error) => Err(error),
             _ => Ok(()),
         }
     }
@@ -66,6 +71,7 @@ impl Instruction {
                 let result = msg::send_for_reply_as::<_, FTStorageEvent>(
                     self.address,
                     self.compensation
+                        .clone()
                         .expect("No compensation for that instruction"),
                     0,
                     0,
@@ -86,8 +92,12 @@ impl Instruction {
     }
 }
 
+/// Creates the instruction that decreases the sender's balance.
+/// The compensation is sent with its own `compensation_hash`,
+/// since the storage has already recorded the `transaction_hash`.
 pub fn create_decrease_instruction(
     transaction_hash: H256,
+    compensation_hash: H256,
     msg_source: &ActorId,
     sender_storage: &ActorId,
     sender: &ActorId,
@@ -102,7 +112,7 @@ pub fn create_decrease_instruction(
             amount,
         },
         Some(FTStorageAction::IncreaseBalance {
-            transaction_hash,
+            transaction_hash: compensation_hash,
             account: *sender,
             amount,
         }),
@@ -125,3 +135,22 @@ pub fn create_increase_instruction(
         None,
     )
 }
+
+pub fn create_increase_balances_instruction(
+    transaction_hash: H256,
+    compensation_hash: H256,
+    storage: &ActorId,
+    accounts: Vec<(ActorId, u128)>,
+) -> Instruction {
+    Instruction::new(
+        *storage,
+        FTStorageAction::IncreaseBalances {
+            transaction_hash,
+            accounts: accounts.clone(),
+        },
+        Some(FTStorageAction::DecreaseBalances {
+            transaction_hash: compensation_hash,
+            accounts,
+        }),
+    )
+}
@@ -1,5 +1,5 @@
 #![no_std]
-use ft_main_io::LogicAction;
+use ft_main_io::{FTokenError, LogicAction, PermitNonce, Role, Roles};
 use gmeta::{In, InOut, Metadata};
 use gstd::{prelude::*, ActorId};
 use primitive_types::H256;
@@ -21,16 +21,26 @@ pub struct FTLogicState {
     pub admin: ActorId,
     pub ftoken_id: ActorId,
     pub transaction_status: Vec<(H256, TransactionStatus)>,
-    pub instructions: Vec<(H256, (Instruction, Instruction))>,
+    pub instructions: Vec<(H256, Vec<Instruction>)>,
     pub storage_code_hash: H256,
-    pub id_to_storage: Vec<(String, ActorId)>,
+    pub shard_bits: u8,
+    pub id_to_storage: Vec<(ShardKey, ActorId)>,
+    pub split_shards: Vec<ShardKey>,
+    pub roles: Roles,
+    pub total_supply: u128,
+    pub max_supply: Option<u128>,
+    pub paused: bool,
+    pub frozen: Vec<ActorId>,
+    pub allowlist_enabled: bool,
+    pub allowlist: Vec<ActorId>,
+    pub shard_moves: Vec<(ShardKey, ShardMove)>,
 }
 
 #[derive(Encode, Decode, TypeInfo, Clone, Debug)]
 pub enum TransactionStatus {
     InProgress,
     Success,
-    Failure,
+    Failure(FTokenError),
 }
 
 #[derive(Debug, Encode, Decode, TypeInfo, Clone)]
@@ -44,27 +54,197 @@ pub enum FTLogicAction {
     GetPermitId(ActorId),
     Clear(H256),
     UpdateStorageCodeHash(H256),
-    MigrateStorages,
+    /// Hands the storages over to the new logic contract.
+    MigrateStorages {
+        new_ft_logic_id: ActorId,
+    },
+    ImportStorages(MigrationData),
+    /// The action the `account` has sent to the main contract,
+    /// it's done if the account has the role the action requires.
+    Admin {
+        account: ActorId,
+        action: AdminAction,
+    },
+    GetTotalSupply,
+    /// Splits the storage of the shard into two storages for the finer key prefixes.
+    /// The accounts are locked in the storage once they're exported. If the message runs out
+    /// of gas, it can be sent again to continue the move once the lease of the previous message expires.
+    /// Replies with `FTokenError::TransactionsInProgress` if there are transactions
+    /// in progress that may involve the storage, the move is continued by the next message then.
+    SplitShard(ShardKey),
+    /// Moves the accounts of the shard to a new storage created from the current
+    /// `storage_code_hash` in the same way as `SplitShard` does.
+    UpgradeStorage(ShardKey),
+    GetAllowance {
+        owner: ActorId,
+        spender: ActorId,
+    },
+    SetAdmin(ActorId),
+    HasRole {
+        role: Role,
+        account: ActorId,
+    },
+    /// Clears the statuses of the transactions imported from the previous logic contract.
+    ClearTransactions(Vec<H256>),
+    /// Replies with the `MigrationData` the new logic contract imports
+    /// before the storages are handed over to it.
+    ExportMigrationData,
+}
+
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
+pub enum AdminAction {
+    /// Requires `Role::Admin`.
+    GrantRole {
+        role: Role,
+        account: ActorId,
+    },
+    /// Requires `Role::Admin`.
+    RevokeRole {
+        role: Role,
+        account: ActorId,
+    },
+    RenounceRole(Role),
+    /// Requires `Role::Pauser`.
+    Pause,
+    /// Requires `Role::Pauser`.
+    Unpause,
+    /// Requires `Role::Compliance`.
+    Freeze(ActorId),
+    /// Requires `Role::Compliance`.
+    Unfreeze(ActorId),
+    /// Requires `Role::Compliance`.
+    AddToAllowlist(ActorId),
+    /// Requires `Role::Compliance`.
+    RemoveFromAllowlist(ActorId),
 }
 
 #[derive(Encode, Decode, TypeInfo)]
 pub enum FTLogicEvent {
     Ok,
-    Err,
+    Err(FTokenError),
     Balance(u128),
     PermitId(u128),
+    MigrationData(MigrationData),
+    TotalSupply(u128),
+    Allowance(u128),
+    HasRole(bool),
 }
 
+/// The state that is handed over from the old logic contract to the new one
+/// when the logic contract is upgraded.
+#[derive(Encode, Decode, TypeInfo, Clone, Debug)]
+pub struct MigrationData {
+    pub admin: ActorId,
+    pub shard_bits: u8,
+    pub id_to_storage: Vec<(ShardKey, ActorId)>,
+    pub split_shards: Vec<ShardKey>,
+    pub instructions: Vec<(H256, Vec<Instruction>)>,
+    pub transaction_status: Vec<(H256, TransactionStatus)>,
+    pub roles: Roles,
+    pub total_supply: u128,
+    pub max_supply: Option<u128>,
+    pub paused: bool,
+    pub frozen: Vec<ActorId>,
+    pub allowlist_enabled: bool,
+    pub allowlist: Vec<ActorId>,
+}
+
+/// The message the owner signs to approve the spender with `LogicAction::Permit`.
 #[derive(Encode, Debug, Decode, TypeInfo, Copy, Clone)]
 pub struct PermitUnsigned {
+    pub domain: PermitDomain,
     pub owner_account: ActorId,
     pub approved_account: ActorId,
     pub amount: u128,
-    pub permit_id: u128,
+    pub permit_id: PermitNonce,
+    /// The last block timestamp (in milliseconds) at which the permit is valid.
+    pub deadline: u64,
+}
+
+/// The message the sender signs to transfer the tokens with
+/// `LogicAction::TransferWithAuthorization`.
+#[derive(Encode, Debug, Decode, TypeInfo, Copy, Clone)]
+pub struct TransferAuthorization {
+    pub domain: PermitDomain,
+    pub sender: ActorId,
+    pub recipient: ActorId,
+    pub amount: u128,
+    pub valid_after: u64,
+    pub valid_before: u64,
+    pub nonce: H256,
+}
+
+/// Binds a signed permit to the token deployment and the version of the permit format,
+/// so the signature can't be replayed against another token.
+#[derive(Encode, Debug, Decode, TypeInfo, Copy, Clone)]
+pub struct PermitDomain {
+    /// The address of the main contract of the token.
+    pub ftoken_id: ActorId,
+    pub version: u32,
 }
 
 #[derive(Encode, Decode, TypeInfo)]
 pub struct InitFTLogic {
     pub admin: ActorId,
     pub storage_code_hash: H256,
+    pub max_supply: Option<u128>,
+    pub shard_bits: u8,
+    pub allowlist: bool,
+    /// The privileged actions are confirmed by the signers of the main contract,
+    /// so the admin has no implicit roles.
+    pub multisig: bool,
+}
+
+/// The maximum number of the leading address bits that select the storage of an account,
+/// so there can be up to 4096 storages.
+pub const MAX_SHARD_BITS: u8 = 12;
+
+/// The version of the permit format in `PermitDomain`.
+pub const PERMIT_VERSION: u32 = 1;
+
+/// The key of a storage: the storage keeps the accounts
+/// whose addresses start with the `bits` leading bits equal to `prefix`.
+#[derive(Encode, Decode, TypeInfo, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShardKey {
+    pub prefix: u32,
+    pub bits: u8,
+}
+
+impl ShardKey {
+    /// Returns the key of the shard with `bits` leading bits that contains the account.
+    pub fn of(account: &ActorId, bits: u8) -> Self {
+        let bytes = account.as_ref();
+        let leading_bits = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        Self {
+            prefix: leading_bits
+                .checked_shr(u32::BITS - u32::from(bits))
+                .unwrap_or_default(),
+            bits,
+        }
+    }
+
+    pub fn contains(&self, account: &ActorId) -> bool {
+        Self::of(account, self.bits) == *self
+    }
+
+    /// Returns the keys of the two shards the shard is split into.
+    pub fn children(&self) -> [Self; 2] {
+        [0, 1].map(|bit| Self {
+            prefix: (self.prefix << 1) | bit,
+            bits: self.bits + 1,
+        })
+    }
+}
+
+/// The move of the accounts from the `source` storage to the `targets`,
+/// each account goes to the target whose key contains it.
+#[derive(Encode, Decode, TypeInfo, Clone, Debug)]
+pub struct ShardMove {
+    pub source: ActorId,
+    pub targets: Vec<(ShardKey, ActorId)>,
+    /// The last account that has already been moved.
+    pub last_moved: Option<ActorId>,
+    /// The block until which the move is continued by the message that drives it,
+    /// the other messages can't continue it before that block.
+    pub driven_until: u32,
 }
@@ -1,25 +1,53 @@
 #![no_std]
 use ft_logic_io::instruction::*;
 use ft_logic_io::*;
-use ft_main_io::LogicAction;
+use ft_main_io::{FTokenError, LogicAction, MultiSignature, PermitNonce, Role, Roles};
+use ft_storage_io::FTStorageAction;
 use gstd::{exec, msg, prelude::*, prog::ProgramGenerator, ActorId};
 
 mod messages;
-use hashbrown::HashMap;
+mod signature;
+use hashbrown::{HashMap, HashSet};
 use messages::*;
-use primitive_types::{H256, H512};
+use primitive_types::H256;
 
 const GAS_STORAGE_CREATION: u64 = 3_000_000_000;
 const DELAY: u32 = 600_000;
+/// The number of accounts moved between storages in one message.
+const MOVE_CHUNK_SIZE: u32 = 100;
+/// The number of blocks a shard move is leased to the message that drives it,
+/// the lease is renewed after each moved chunk.
+const MOVE_LEASE: u32 = 1_000;
 
 #[derive(Default)]
 struct FTLogic {
     admin: ActorId,
     ftoken_id: ActorId,
     transaction_status: HashMap<H256, TransactionStatus>,
-    instructions: HashMap<H256, (Instruction, Instruction)>,
+    instructions: HashMap<H256, Vec<Instruction>>,
     storage_code_hash: H256,
-    id_to_storage: HashMap<String, ActorId>,
+    shard_bits: u8,
+    id_to_storage: HashMap<ShardKey, ActorId>,
+    split_shards: HashSet<ShardKey>,
+    shard_moves: HashMap<ShardKey, ShardMove>,
+    roles: Roles,
+    total_supply: u128,
+    max_supply: Option<u128>,
+    paused: bool,
+    frozen: HashSet<ActorId>,
+    allowlist_enabled: bool,
+    allowlist: HashSet<ActorId>,
+    multisig: bool,
+    /// The amounts of the mints that are in progress, they're reserved under the maximum supply
+    /// until the transaction is completed or its status is cleared.
+    pending_mints: HashMap<H256, u128>,
+    /// The amounts of the batch mints in progress that have been added to the total supply,
+    /// so the resent transaction only counts the credits done or compensated since then.
+    minted: HashMap<H256, u128>,
+    /// The transactions whose signatures and validity periods have been checked,
+    /// a resent transaction isn't checked again, so it's completed even if it has expired since then.
+    authorized_transactions: HashSet<H256>,
+    migrated: bool,
 }
 
 static mut FT_LOGIC: Option<FTLogic> = None;
@@ -33,8 +61,26 @@ impl FTLogic {
     /// * `action`: the message payload.
     async fn message(&mut self, transaction_hash: H256, account: &ActorId, payload: &[u8]) {
         self.assert_main_contract();
+        assert!(
+            !self.migrated,
+            "The logic contract has been migrated to a new one"
+        );
         let action = LogicAction::decode(&mut &payload[..]).expect("Can't decode `Action`");
 
+        // The transactions that have already started are completed
+        // even if the token is paused or the accounts are frozen.
+        if !self.transaction_status.contains_key(&transaction_hash) {
+            let check = if self.paused {
+                Err(FTokenError::Paused)
+            } else {
+                self.check_not_frozen(account, &action)
+            };
+            if let Err(error) = check {
+                reply_err(error);
+                return;
+            }
+        }
+
         let transaction_status = self
             .transaction_status
             .get(&transaction_hash)
@@ -43,7 +89,7 @@ impl FTLogic {
         match transaction_status {
             // The transaction has already been made but there wasn't enough gas for a message reply.
             TransactionStatus::Success => reply_ok(),
-            TransactionStatus::Failure => reply_err(),
+            TransactionStatus::Failure(error) => reply_err(*error),
             // The transaction took place for the first time
             // Or there was not enough gas to change the `TransactionStatus`.
             TransactionStatus::InProgress => {
@@ -52,7 +98,8 @@ impl FTLogic {
                     .insert(transaction_hash, TransactionStatus::InProgress);
                 match action {
                     LogicAction::Mint { recipient, amount } => {
-                        self.mint(transaction_hash, &recipient, amount).await;
+                        self.mint(transaction_hash, account, &recipient, amount)
+                            .await;
                     }
                     LogicAction::Burn { sender, amount } => {
                         self.burn(transaction_hash, account, &sender, amount).await;
@@ -69,21 +116,59 @@ impl FTLogic {
                         approved_account,
                         amount,
                     } => {
-                        self.approve(transaction_hash, account, &approved_account, amount)
-                            .await;
+                        self.approve(
+                            transaction_hash,
+                            account,
+                            &approved_account,
+                            amount,
+                            AllowanceChange::Set,
+                        )
+                        .await;
+                    }
+                    LogicAction::IncreaseAllowance {
+                        approved_account,
+                        amount,
+                    } => {
+                        self.approve(
+                            transaction_hash,
+                            account,
+                            &approved_account,
+                            amount,
+                            AllowanceChange::Increase,
+                        )
+                        .await;
+                    }
+                    LogicAction::DecreaseAllowance {
+                        approved_account,
+                        amount,
+                    } => {
+                        self.approve(
+                            transaction_hash,
+                            account,
+                            &approved_account,
+                            amount,
+                            AllowanceChange::Decrease,
+                        )
+                        .await;
                     }
                     LogicAction::Permit {
                         owner_account,
                         approved_account,
                         amount,
                         permit_id,
+                        deadline,
                         sign,
                     } => {
                         let payload = PermitUnsigned {
+                            domain: PermitDomain {
+                                ftoken_id: self.ftoken_id,
+                                version: PERMIT_VERSION,
+                            },
                             owner_account,
                             approved_account,
                             amount,
                             permit_id,
+                            deadline,
                         };
                         self.permit(
                             transaction_hash,
@@ -95,29 +180,135 @@ impl FTLogic {
                         )
                         .await;
                     }
+                    LogicAction::TransferWithAuthorization {
+                        sender,
+                        recipient,
+                        amount,
+                        valid_after,
+                        valid_before,
+                        nonce,
+                        sign,
+                    } => {
+                        let authorization = TransferAuthorization {
+                            domain: PermitDomain {
+                                ftoken_id: self.ftoken_id,
+                                version: PERMIT_VERSION,
+                            },
+                            sender,
+                            recipient,
+                            amount,
+                            valid_after,
+                            valid_before,
+                            nonce,
+                        };
+                        self.transfer_with_authorization(transaction_hash, &authorization, &sign)
+                            .await;
+                    }
+                    LogicAction::BatchTransfer { sender, transfers } => {
+                        self.batch_transfer(transaction_hash, account, &sender, transfers)
+                            .await;
+                    }
+                    LogicAction::BatchMint(mints) => {
+                        self.batch_mint(transaction_hash, account, mints).await;
+                    }
                 }
             }
         }
     }
 
-    async fn mint(&mut self, transaction_hash: H256, recipient: &ActorId, amount: u128) {
-        let recipient_storage = self.get_storage_address(recipient);
-
-        let result =
-            increase_balance(transaction_hash, &recipient_storage, recipient, amount).await;
+    /// Mints the tokens with one credit instruction,
+    /// so the total supply is counted the same way as in a batch mint.
+    async fn mint(
+        &mut self,
+        transaction_hash: H256,
+        account: &ActorId,
+        recipient: &ActorId,
+        amount: u128,
+    ) {
+        self.batch_mint(transaction_hash, account, vec![(*recipient, amount)])
+            .await;
+    }
 
-        match result {
-            Ok(()) => {
-                self.transaction_status
-                    .insert(transaction_hash, TransactionStatus::Success);
-                reply_ok()
+    /// Mints the tokens to the recipients of each storage with one message.
+    ///
+    /// Arguments:
+    /// * `transaction_hash`: the hash associated with that transaction;
+    /// * `account`: the account that mints the tokens;
+    /// * `mints`: the recipients and the amounts they receive.
+    async fn batch_mint(
+        &mut self,
+        transaction_hash: H256,
+        account: &ActorId,
+        mints: Vec<(ActorId, u128)>,
+    ) {
+        let Some(total_amount) = mints
+            .iter()
+            .try_fold(0u128, |total, (_, amount)| total.checked_add(*amount))
+        else {
+            self.complete_transaction(transaction_hash, Err(FTokenError::AmountOverflow));
+            return;
+        };
+        if !self.instructions.contains_key(&transaction_hash) {
+            if !self.has_role(Role::Minter, account) {
+                self.complete_transaction(transaction_hash, Err(FTokenError::NotMinter));
+                return;
             }
-            Err(()) => {
-                self.transaction_status
-                    .insert(transaction_hash, TransactionStatus::Failure);
-                reply_err();
+            let recipients: Vec<&ActorId> = mints.iter().map(|(recipient, _)| recipient).collect();
+            if let Err(error) = self.check_allowlisted(&recipients) {
+                self.complete_transaction(transaction_hash, Err(error));
+                return;
+            }
+            if self.exceeds_max_supply(transaction_hash, total_amount) {
+                self.complete_transaction(transaction_hash, Err(FTokenError::MaxSupplyExceeded));
+                return;
+            }
+            match self.create_credit_instructions(transaction_hash, &mints) {
+                Ok(instructions) => {
+                    self.instructions.insert(transaction_hash, instructions);
+                }
+                Err(error) => {
+                    self.complete_transaction(transaction_hash, Err(error));
+                    return;
+                }
             }
         }
+        self.pending_mints.insert(transaction_hash, total_amount);
+
+        let result = self.run_instructions(transaction_hash).await;
+        self.count_minted(transaction_hash);
+        self.complete_transaction(transaction_hash, result);
+    }
+
+    /// Adds the credits of the batch mint that have been done and not compensated
+    /// to the total supply, even if the transaction has failed.
+    /// The amount that has already been counted by the previous runs
+    /// of the transaction is taken into account.
+    fn count_minted(&mut self, transaction_hash: H256) {
+        let minted = self
+            .instructions
+            .get(&transaction_hash)
+            .map_or(0, |instructions| {
+                instructions
+                    .iter()
+                    .filter(|instruction| {
+                        matches!(instruction.state, InstructionState::ScheduledAbort)
+                    })
+                    .filter_map(|instruction| match &instruction.transaction {
+                        FTStorageAction::IncreaseBalances { accounts, .. } => Some(accounts),
+                        _ => None,
+                    })
+                    .flatten()
+                    .fold(0u128, |total, (_, amount)| total.saturating_add(*amount))
+            });
+        let counted = self
+            .minted
+            .insert(transaction_hash, minted)
+            .unwrap_or_default();
+        self.total_supply = if minted >= counted {
+            self.total_supply.saturating_add(minted - counted)
+        } else {
+            self.total_supply.saturating_sub(counted - minted)
+        };
     }
 
     async fn burn(
@@ -127,23 +318,33 @@ impl FTLogic {
         sender: &ActorId,
         amount: u128,
     ) {
-        let sender_storage = self.get_storage_address(sender);
+        let sender_storage = match self.get_storage_address(sender) {
+            Ok(storage) => storage,
+            Err(error) => {
+                self.complete_transaction(transaction_hash, Err(error));
+                return;
+            }
+        };
 
-        let result =
-            decrease_balance(transaction_hash, &sender_storage, account, sender, amount).await;
+        // The burner burns the tokens of any account without an allowance.
+        let msg_source = if self.has_role(Role::Burner, account) {
+            sender
+        } else {
+            account
+        };
+        let result = decrease_balance(
+            transaction_hash,
+            &sender_storage,
+            msg_source,
+            sender,
+            amount,
+        )
+        .await;
 
-        match result {
-            Ok(()) => {
-                self.transaction_status
-                    .insert(transaction_hash, TransactionStatus::Success);
-                reply_ok()
-            }
-            Err(()) => {
-                self.transaction_status
-                    .insert(transaction_hash, TransactionStatus::Failure);
-                reply_err();
-            }
+        if result.is_ok() {
+            self.total_supply = self.total_supply.saturating_sub(amount);
         }
+        self.complete_transaction(transaction_hash, result);
     }
 
     async fn transfer(
@@ -154,11 +355,29 @@ impl FTLogic {
         recipient: &ActorId,
         amount: u128,
     ) {
-        let sender_storage = self.get_storage_address(sender);
-        let recipient_storage = self.get_storage_address(recipient);
+        // The transfer that has already started between storages is completed
+        // even if the accounts have been removed from the allowlist.
+        if !self.instructions.contains_key(&transaction_hash) {
+            if let Err(error) = self.check_allowlisted(&[sender, recipient]) {
+                self.complete_transaction(transaction_hash, Err(error));
+                return;
+            }
+        }
+
+        let storages = self.get_storage_address(sender).and_then(|sender_storage| {
+            self.get_storage_address(recipient)
+                .map(|recipient_storage| (sender_storage, recipient_storage))
+        });
+        let (sender_storage, recipient_storage) = match storages {
+            Ok(storages) => storages,
+            Err(error) => {
+                self.complete_transaction(transaction_hash, Err(error));
+                return;
+            }
+        };
 
         if recipient_storage == sender_storage {
-            self.transfer_single_storage(
+            let result = transfer(
                 transaction_hash,
                 &sender_storage,
                 msg_source,
@@ -167,14 +386,15 @@ impl FTLogic {
                 amount,
             )
             .await;
+            self.complete_transaction(transaction_hash, result);
             return;
         }
-        let (decrease_instruction, increase_instruction) = self
-            .instructions
+        self.instructions
             .entry(transaction_hash)
             .or_insert_with(|| {
                 let decrease_instruction = create_decrease_instruction(
                     transaction_hash,
+                    compensation_hash(transaction_hash),
                     msg_source,
                     &sender_storage,
                     sender,
@@ -186,63 +406,137 @@ impl FTLogic {
                     recipient,
                     amount,
                 );
-                (decrease_instruction, increase_instruction)
+                vec![decrease_instruction, increase_instruction]
             });
+        let result = self.run_instructions(transaction_hash).await;
+        self.complete_transaction(transaction_hash, result);
+    }
 
-        if decrease_instruction.start().await.is_err() {
-            self.transaction_status
-                .insert(transaction_hash, TransactionStatus::Failure);
-            reply_err();
-            return;
-        }
-
-        match increase_instruction.start().await {
-            Err(_) => {
-                if decrease_instruction.abort().await.is_ok() {
-                    self.transaction_status
-                        .insert(transaction_hash, TransactionStatus::Failure);
-                    reply_err();
+    /// Debits the total amount from the sender with one message
+    /// and credits the recipients of each storage with one message.
+    ///
+    /// Arguments:
+    /// * `transaction_hash`: the hash associated with that transaction;
+    /// * `msg_source`: the account that sends the tokens on behalf of the sender;
+    /// * `sender`: the account whose tokens are transferred;
+    /// * `transfers`: the recipients and the amounts they receive.
+    async fn batch_transfer(
+        &mut self,
+        transaction_hash: H256,
+        msg_source: &ActorId,
+        sender: &ActorId,
+        transfers: Vec<(ActorId, u128)>,
+    ) {
+        if !self.instructions.contains_key(&transaction_hash) {
+            let mut accounts: Vec<&ActorId> =
+                transfers.iter().map(|(recipient, _)| recipient).collect();
+            accounts.push(sender);
+            let instructions = self.check_allowlisted(&accounts).and_then(|()| {
+                self.create_batch_transfer_instructions(
+                    transaction_hash,
+                    msg_source,
+                    sender,
+                    &transfers,
+                )
+            });
+            match instructions {
+                Ok(instructions) => {
+                    self.instructions.insert(transaction_hash, instructions);
+                }
+                Err(error) => {
+                    self.complete_transaction(transaction_hash, Err(error));
+                    return;
                 }
-            }
-            Ok(_) => {
-                self.transaction_status
-                    .insert(transaction_hash, TransactionStatus::Success);
-                reply_ok();
             }
         }
+        let result = self.run_instructions(transaction_hash).await;
+        self.complete_transaction(transaction_hash, result);
     }
 
-    async fn transfer_single_storage(
+    /// Debits the sender with one message and then credits the recipients.
+    fn create_batch_transfer_instructions(
         &mut self,
         transaction_hash: H256,
-        storage_id: &ActorId,
         msg_source: &ActorId,
         sender: &ActorId,
-        recipient: &ActorId,
-        amount: u128,
-    ) {
-        let result = transfer(
+        transfers: &[(ActorId, u128)],
+    ) -> Result<Vec<Instruction>, FTokenError> {
+        let total_amount = transfers
+            .iter()
+            .try_fold(0u128, |total, (_, amount)| total.checked_add(*amount))
+            .ok_or(FTokenError::AmountOverflow)?;
+        let sender_storage = self.get_storage_address(sender)?;
+        let mut instructions = vec![create_decrease_instruction(
             transaction_hash,
-            storage_id,
+            compensation_hash(transaction_hash),
             msg_source,
+            &sender_storage,
             sender,
-            recipient,
-            amount,
-        )
-        .await;
+            total_amount,
+        )];
+        instructions.extend(self.create_credit_instructions(transaction_hash, transfers)?);
+        Ok(instructions)
+    }
 
-        match result {
-            Ok(()) => {
-                self.transaction_status
-                    .insert(transaction_hash, TransactionStatus::Success);
-                reply_ok()
+    /// Groups the recipients by their storages and creates the instructions
+    /// that credit the recipients of each storage with one message.
+    /// The messages have the hashes derived from the transaction hash,
+    /// so the storage that has already debited the sender in the same transaction
+    /// can credit the recipients.
+    fn create_credit_instructions(
+        &mut self,
+        transaction_hash: H256,
+        credits: &[(ActorId, u128)],
+    ) -> Result<Vec<Instruction>, FTokenError> {
+        let mut storage_credits: BTreeMap<ActorId, Vec<(ActorId, u128)>> = BTreeMap::new();
+        for (recipient, amount) in credits {
+            let recipient_storage = self.get_storage_address(recipient)?;
+            storage_credits
+                .entry(recipient_storage)
+                .or_default()
+                .push((*recipient, *amount));
+        }
+
+        let mut instructions = Vec::with_capacity(storage_credits.len());
+        for (index, (storage, accounts)) in storage_credits.into_iter().enumerate() {
+            let credit_hash: H256 =
+                sp_core_hashing::blake2_256(&(transaction_hash, index as u32).encode()).into();
+            instructions.push(create_increase_balances_instruction(
+                credit_hash,
+                compensation_hash(credit_hash),
+                &storage,
+                accounts,
+            ));
+        }
+        Ok(instructions)
+    }
+
+    /// Starts the instructions of the transaction one by one and returns the result.
+    /// If one of them fails, the started ones are aborted in the reverse order.
+    /// If an abort fails, it returns `FTokenError::CompensationFailed`
+    /// and the resent transaction continues the compensation.
+    async fn run_instructions(&mut self, transaction_hash: H256) -> Result<(), FTokenError> {
+        let instructions = self
+            .instructions
+            .get_mut(&transaction_hash)
+            .expect("There are no instructions for the transaction");
+        let mut result = Ok(());
+        let mut started = 0;
+        for instruction in instructions.iter_mut() {
+            if let Err(error) = instruction.start().await {
+                result = Err(error);
+                break;
             }
-            Err(()) => {
-                self.transaction_status
-                    .insert(transaction_hash, TransactionStatus::Failure);
-                reply_err();
+            started += 1;
+        }
+        if result.is_err() {
+            for instruction in instructions[..started].iter_mut().rev() {
+                if instruction.abort().await.is_err() {
+                    return Err(FTokenError::CompensationFailed);
+                }
             }
         }
+        result
     }
 
     async fn approve(
@@ -251,37 +545,31 @@ impl FTLogic {
         account: &ActorId,
         approved_account: &ActorId,
         amount: u128,
+        change: AllowanceChange,
     ) {
-        self.transaction_status
-            .insert(transaction_hash, TransactionStatus::InProgress);
-        let account_storage = self.get_storage_address(account);
+        let account_storage = match self.get_storage_address(account) {
+            Ok(storage) => storage,
+            Err(error) => {
+                self.complete_transaction(transaction_hash, Err(error));
+                return;
+            }
+        };
 
-        let result = approve(
+        let result = change_allowance(
             transaction_hash,
             &account_storage,
             account,
             approved_account,
             amount,
+            change,
         )
         .await;
 
-        match result {
-            Ok(()) => {
-                self.transaction_status
-                    .insert(transaction_hash, TransactionStatus::Success);
-                reply_ok()
-            }
-            Err(()) => {
-                self.transaction_status
-                    .insert(transaction_hash, TransactionStatus::Failure);
-                reply_err();
-            }
-        }
+        self.complete_transaction(transaction_hash, result);
     }
 
-    fn check_signature(message: &PermitUnsigned, owner: &ActorId, sign: &H512) -> bool {
-        let message_u8 = message.encode();
-        light_sr25519::verify(sign.as_bytes(), message_u8, owner).is_ok()
+    fn check_signature(message: &PermitUnsigned, owner: &ActorId, sign: &MultiSignature) -> bool {
+        signature::verify(sign, &message.encode(), owner)
     }
 
     async fn permit(
@@ -290,70 +578,598 @@ impl FTLogic {
         owner: &ActorId,
         spender: &ActorId,
         amount: u128,
-        owner_sign: &H512,
+        owner_sign: &MultiSignature,
         message: &PermitUnsigned,
     ) {
-        if !FTLogic::check_signature(message, owner, owner_sign) {
-            reply_err();
+        if !self.authorized_transactions.contains(&transaction_hash) {
+            if exec::block_timestamp() > message.deadline {
+                self.complete_transaction(transaction_hash, Err(FTokenError::PermitExpired));
+                return;
+            }
+
+            if !FTLogic::check_signature(message, owner, owner_sign) {
+                self.complete_transaction(transaction_hash, Err(FTokenError::InvalidSignature));
+                return;
+            }
+            self.authorized_transactions.insert(transaction_hash);
+        }
+
+        let nonce_result = match message.permit_id {
+            PermitNonce::Sequential(permit_id) => {
+                self.check_and_increment_permit_id(transaction_hash, owner, &permit_id)
+                    .await
+            }
+            PermitNonce::Unordered(nonce) => {
+                self.use_permit_nonce(transaction_hash, owner, nonce).await
+            }
+        };
+        if let Err(error) = nonce_result {
+            self.complete_transaction(transaction_hash, Err(error));
             return;
         }
 
-        self.transaction_status
-            .insert(transaction_hash, TransactionStatus::InProgress);
+        let account_storage = match self.get_storage_address(owner) {
+            Ok(storage) => storage,
+            Err(error) => {
+                self.complete_transaction(transaction_hash, Err(error));
+                return;
+            }
+        };
+        let result = change_allowance(
+            transaction_hash,
+            &account_storage,
+            owner,
+            spender,
+            amount,
+            AllowanceChange::Set,
+        )
+        .await;
+        self.complete_transaction(transaction_hash, result);
+    }
+
+    async fn transfer_with_authorization(
+        &mut self,
+        transaction_hash: H256,
+        authorization: &TransferAuthorization,
+        sign: &MultiSignature,
+    ) {
+        let sender = &authorization.sender;
+        if !self.authorized_transactions.contains(&transaction_hash) {
+            let now = exec::block_timestamp();
+            if now <= authorization.valid_after || now >= authorization.valid_before {
+                self.complete_transaction(
+                    transaction_hash,
+                    Err(FTokenError::AuthorizationNotValid),
+                );
+                return;
+            }
 
-        if !self
-            .check_and_increment_permit_id(transaction_hash, owner, &message.permit_id)
-            .await
+            if !signature::verify(sign, &authorization.encode(), sender) {
+                self.complete_transaction(transaction_hash, Err(FTokenError::InvalidSignature));
+                return;
+            }
+            self.authorized_transactions.insert(transaction_hash);
+        }
+
+        let sender_storage = match self.get_storage_address(sender) {
+            Ok(storage) => storage,
+            Err(error) => {
+                self.complete_transaction(transaction_hash, Err(error));
+                return;
+            }
+        };
+        // The sender storage also receives the messages of the transfer itself,
+        // so the nonce is used under its own transaction hash.
+        // The storage replies with the saved result to a resent transaction.
+        let nonce_transaction_hash: H256 =
+            sp_core_hashing::blake2_256(&(transaction_hash, authorization.nonce).encode()).into();
+        if let Err(error) = use_nonce(
+            nonce_transaction_hash,
+            &sender_storage,
+            sender,
+            authorization.nonce,
+        )
+        .await
         {
-            self.transaction_status
-                .insert(transaction_hash, TransactionStatus::Failure);
-            reply_err();
+            self.complete_transaction(transaction_hash, Err(error));
             return;
         }
 
-        let account_storage = self.get_storage_address(owner);
-        let result = approve(transaction_hash, &account_storage, owner, spender, amount).await;
+        self.transfer(
+            transaction_hash,
+            sender,
+            sender,
+            &authorization.recipient,
+            authorization.amount,
+        )
+        .await;
+    }
+
+    /// Saves the result of the transaction and replies with it.
+    /// The transaction whose compensation has failed stays in progress,
+    /// so the resent transaction resumes the compensation.
+    fn complete_transaction(&mut self, transaction_hash: H256, result: Result<(), FTokenError>) {
+        if result == Err(FTokenError::CompensationFailed) {
+            reply_err(FTokenError::CompensationFailed);
+            return;
+        }
+        self.instructions.remove(&transaction_hash);
+        self.pending_mints.remove(&transaction_hash);
+        self.minted.remove(&transaction_hash);
+        self.authorized_transactions.remove(&transaction_hash);
         match result {
             Ok(()) => {
                 self.transaction_status
                     .insert(transaction_hash, TransactionStatus::Success);
-                reply_ok()
+                reply_ok();
             }
-            Err(()) => {
+            Err(error) => {
                 self.transaction_status
-                    .insert(transaction_hash, TransactionStatus::Failure);
-                reply_err();
+                    .insert(transaction_hash, TransactionStatus::Failure(error));
+                reply_err(error);
             }
         }
     }
 
+    /// Hands the storages over to the new logic contract
+    /// once it has imported the migration data.
+    /// The contract stops serving messages only after every storage has accepted
+    /// the new logic contract. The storages that have already been handed over
+    /// accept the message again, so a failed migration can be retried.
+    ///
+    /// Arguments:
+    /// * `new_ft_logic_id`: the address of the new logic contract.
+    async fn migrate_storages(&mut self, new_ft_logic_id: &ActorId) {
+        self.assert_main_contract();
+        assert!(
+            self.shard_moves.is_empty(),
+            "The storages can't be migrated while shards are being moved"
+        );
+        for storage_id in self.id_to_storage.values() {
+            if update_logic_contract(storage_id, new_ft_logic_id)
+                .await
+                .is_err()
+            {
+                reply_err(FTokenError::MessageFailed);
+                return;
+            }
+        }
+        self.migrated = true;
+        reply_ok();
+    }
+
+    /// Replies with the routing table and the transaction statuses,
+    /// so that the main contract can pass them to the new logic contract.
+    /// The data isn't exported while any transaction is in progress,
+    /// since its remaining steps would still be run by this contract.
+    fn export_migration_data(&self) {
+        self.assert_main_contract();
+        assert!(
+            self.shard_moves.is_empty(),
+            "The storages can't be migrated while shards are being moved"
+        );
+        if self
+            .transaction_status
+            .values()
+            .any(|status| matches!(status, TransactionStatus::InProgress))
+        {
+            reply_err(FTokenError::TransactionsInProgress);
+            return;
+        }
+        let migration_data = MigrationData {
+            admin: self.admin,
+            shard_bits: self.shard_bits,
+            id_to_storage: self
+                .id_to_storage
+                .iter()
+                .map(|(key, value)| (*key, *value))
+                .collect(),
+            split_shards: self.split_shards.iter().copied().collect(),
+            instructions: self
+                .instructions
+                .iter()
+                .map(|(key, value)| (*key, value.clone()))
+                .collect(),
+            transaction_status: self
+                .transaction_status
+                .iter()
+                .map(|(key, value)| (*key, value.clone()))
+                .collect(),
+            roles: self.roles.clone(),
+            total_supply: self.total_supply,
+            max_supply: self.max_supply,
+            paused: self.paused,
+            frozen: self.frozen.iter().copied().collect(),
+            allowlist_enabled: self.allowlist_enabled,
+            allowlist: self.allowlist.iter().copied().collect(),
+        };
+        msg::reply(FTLogicEvent::MigrationData(migration_data), 0)
+            .expect("Error in a reply `FTLogicEvent::MigrationData`");
+    }
+
+    /// Accepts the state of the previous logic contract.
+    fn import_storages(&mut self, migration_data: MigrationData) {
+        self.assert_main_contract();
+        self.admin = migration_data.admin;
+        self.shard_bits = migration_data.shard_bits;
+        self.id_to_storage.extend(migration_data.id_to_storage);
+        self.split_shards.extend(migration_data.split_shards);
+        self.instructions.extend(migration_data.instructions);
+        // one delayed message clears all the imported statuses
+        let transaction_hashes: Vec<H256> = migration_data
+            .transaction_status
+            .iter()
+            .map(|(transaction_hash, _)| *transaction_hash)
+            .collect();
+        if !transaction_hashes.is_empty() {
+            msg::send_delayed(
+                exec::program_id(),
+                FTLogicAction::ClearTransactions(transaction_hashes),
+                0,
+                DELAY,
+            )
+            .expect("Error in sending a delayed message `FTLogicAction::ClearTransactions`");
+        }
+        self.transaction_status
+            .extend(migration_data.transaction_status);
+        self.roles = migration_data.roles;
+        self.total_supply = migration_data.total_supply;
+        self.max_supply = migration_data.max_supply;
+        self.paused = migration_data.paused;
+        self.frozen.extend(migration_data.frozen);
+        self.allowlist_enabled = migration_data.allowlist_enabled;
+        self.allowlist.extend(migration_data.allowlist);
+        reply_ok();
+    }
+
+    /// The privileged action sent to the main contract.
+    ///
+    /// Arguments:
+    /// * `account`: the account that sent the action to the main contract;
+    /// * `action`: the action that is done if the account has the role it requires.
+    fn admin_action(&mut self, account: &ActorId, action: AdminAction) {
+        self.assert_main_contract();
+        let required_role = match action {
+            AdminAction::GrantRole { .. } | AdminAction::RevokeRole { .. } => Some(Role::Admin),
+            AdminAction::RenounceRole(_) => None,
+            AdminAction::Pause | AdminAction::Unpause => Some(Role::Pauser),
+            AdminAction::Freeze(_)
+            | AdminAction::Unfreeze(_)
+            | AdminAction::AddToAllowlist(_)
+            | AdminAction::RemoveFromAllowlist(_) => Some(Role::Compliance),
+        };
+        if let Some(role) = required_role {
+            if !self.has_role(role, account) {
+                reply_err(FTokenError::MissingRole);
+                return;
+            }
+        }
+        match action {
+            AdminAction::GrantRole { role, account } => self.roles.grant(role, account),
+            AdminAction::RevokeRole { role, account } => self.roles.revoke(role, &account),
+            AdminAction::RenounceRole(role) => self.roles.revoke(role, account),
+            AdminAction::Pause => self.paused = true,
+            AdminAction::Unpause => self.paused = false,
+            AdminAction::Freeze(account) => {
+                self.frozen.insert(account);
+            }
+            AdminAction::Unfreeze(account) => {
+                self.frozen.remove(&account);
+            }
+            AdminAction::AddToAllowlist(account) => {
+                self.allowlist.insert(account);
+            }
+            AdminAction::RemoveFromAllowlist(account) => {
+                self.allowlist.remove(&account);
+            }
+        }
+        reply_ok();
+    }
+
+    fn set_admin(&mut self, admin: &ActorId) {
+        self.assert_main_contract();
+        self.admin = *admin;
+        reply_ok();
+    }
+
+    fn check_allowlisted(&self, accounts: &[&ActorId]) -> Result<(), FTokenError> {
+        if self.allowlist_enabled
+            && !accounts
+                .iter()
+                .all(|account| self.allowlist.contains(*account))
+        {
+            Err(FTokenError::NotAllowlisted)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Fails if any account that sends, receives or approves the tokens in the action is frozen.
+    fn check_not_frozen(&self, account: &ActorId, action: &LogicAction) -> Result<(), FTokenError> {
+        let accounts = match action {
+            LogicAction::Mint { recipient, .. } => vec![recipient],
+            LogicAction::BatchMint(mints) => mints.iter().map(|(recipient, _)| recipient).collect(),
+            LogicAction::Burn { sender, .. } => vec![account, sender],
+            LogicAction::Transfer {
+                sender, recipient, ..
+            }
+            | LogicAction::TransferWithAuthorization {
+                sender, recipient, ..
+            } => vec![account, sender, recipient],
+            LogicAction::BatchTransfer { sender, transfers } => {
+                let mut accounts = vec![account, sender];
+                accounts.extend(transfers.iter().map(|(recipient, _)| recipient));
+                accounts
+            }
+            LogicAction::Approve { .. }
+            | LogicAction::IncreaseAllowance { .. }
+            | LogicAction::DecreaseAllowance { .. } => vec![account],
+            LogicAction::Permit { owner_account, .. } => vec![owner_account],
+        };
+        if accounts
+            .into_iter()
+            .any(|account| self.frozen.contains(account))
+        {
+            Err(FTokenError::AccountFrozen)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The admin implicitly has all the roles except `Role::Burner`,
+    /// which must be granted explicitly. If the token has signers, the admin has no implicit
+    /// roles, the main contract has them instead for the actions confirmed by the signers.
+    fn has_role(&self, role: Role, account: &ActorId) -> bool {
+        let privileged = if self.multisig {
+            *account == self.ftoken_id
+        } else {
+            *account == self.admin
+        };
+        (privileged && role != Role::Burner) || self.roles.has_role(role, account)
+    }
+
+    fn assert_role(&self, role: Role, account: &ActorId) {
+        assert!(
+            self.has_role(role, account),
+            "The account doesn't have the {role:?} role"
+        );
+    }
+
+    /// Checks whether the mint would push the total supply past the maximum supply.
+    /// The mints that are still in progress are taken into account,
+    /// so concurrent mints can't exceed the cap together.
+    fn exceeds_max_supply(&self, transaction_hash: H256, amount: u128) -> bool {
+        let Some(max_supply) = self.max_supply else {
+            return false;
+        };
+        self.pending_mints
+            .iter()
+            .filter(|(hash, _)| **hash != transaction_hash)
+            .try_fold(self.total_supply, |supply, (_, pending_amount)| {
+                supply.checked_add(*pending_amount)
+            })
+            .and_then(|supply| supply.checked_add(amount))
+            .map_or(true, |supply| supply > max_supply)
+    }
+
     fn update_storage_hash(&mut self, storage_code_hash: H256) {
-        self.assert_admin();
+        self.assert_main_contract();
         self.storage_code_hash = storage_code_hash;
+        reply_ok();
+    }
+
+    /// Splits the shard into two shards with one more bit in the key prefix.
+    /// The accounts are moved to the storages of the new shards
+    /// and then the routing is switched to them.
+    ///
+    /// Arguments:
+    /// * `key`: the key of the shard to split.
+    async fn split_shard(&mut self, key: ShardKey) {
+        self.assert_role(Role::Upgrader, &msg::source());
+        assert!(
+            key.bits < u32::BITS as u8,
+            "The shard can't be split any further"
+        );
+        match self.move_shard(key, &key.children()).await {
+            Ok(()) => {
+                self.split_shards.insert(key);
+                reply_ok();
+            }
+            Err(error) => reply_err(error),
+        }
+    }
+
+    /// Moves the accounts of the shard to a new storage created from the current storage code,
+    /// so the existing shards can be upgraded after `UpdateStorageCodeHash`.
+    ///
+    /// Arguments:
+    /// * `key`: the key of the shard to upgrade.
+    async fn upgrade_storage(&mut self, key: ShardKey) {
+        self.assert_role(Role::Upgrader, &msg::source());
+        match self.move_shard(key, &[key]).await {
+            Ok(()) => reply_ok(),
+            Err(error) => reply_err(error),
+        }
+    }
+
+    /// Moves the accounts of the shard to the new storages created for the `target_keys`
+    /// and switches the routing to them. If the shard move has already been started,
+    /// it's continued from the last moved chunk once the lease of the message
+    /// that has been driving it expires.
+    async fn move_shard(
+        &mut self,
+        key: ShardKey,
+        target_keys: &[ShardKey],
+    ) -> Result<(), FTokenError> {
+        let block_height = exec::block_height();
+        if let Some(shard_move) = self.shard_moves.get_mut(&key) {
+            assert!(
+                shard_move
+                    .targets
+                    .iter()
+                    .map(|(target_key, _)| target_key)
+                    .eq(target_keys.iter()),
+                "Another move of that shard is in progress"
+            );
+            assert!(
+                shard_move.driven_until <= block_height,
+                "The shard move is being driven by another message"
+            );
+            shard_move.driven_until = block_height + MOVE_LEASE;
+        } else {
+            let source = *self
+                .id_to_storage
+                .get(&key)
+                .expect("There is no storage for that shard");
+            self.check_no_transactions_in_progress(&source)?;
+            let targets = target_keys
+                .iter()
+                .map(|target_key| {
+                    let storage = self
+                        .create_storage()
+                        .expect("Error in creating Storage program");
+                    (*target_key, storage)
+                })
+                .collect();
+            self.shard_moves.insert(
+                key,
+                ShardMove {
+                    source,
+                    targets,
+                    last_moved: None,
+                    driven_until: block_height + MOVE_LEASE,
+                },
+            );
+        }
+
+        if let Err(error) = self.move_accounts(&key).await {
+            if let Some(shard_move) = self.shard_moves.get_mut(&key) {
+                shard_move.driven_until = 0;
+            }
+            return Err(error);
+        }
+
+        let shard_move = self
+            .shard_moves
+            .remove(&key)
+            .expect("The shard move must exist");
+        self.id_to_storage.remove(&key);
+        self.id_to_storage.extend(shard_move.targets);
+        Ok(())
+    }
+
+    /// Moves the accounts of the shard in chunks from the source storage to the target ones.
+    /// The progress is saved after each chunk, so the move can be continued
+    /// if the message runs out of gas. The chunks are imported with the hashes
+    /// derived from the source storage and the last account before the chunk,
+    /// so a chunk is never imported twice.
+    ///
+    /// Before each chunk and before the routing is switched, it's checked that
+    /// there are no transactions in progress that may involve the source storage.
+    async fn move_accounts(&mut self, key: &ShardKey) -> Result<(), FTokenError> {
+        loop {
+            let shard_move = self
+                .shard_moves
+                .get(key)
+                .expect("The shard move must exist")
+                .clone();
+            self.check_no_transactions_in_progress(&shard_move.source)?;
+            let accounts =
+                export_accounts(&shard_move.source, shard_move.last_moved, MOVE_CHUNK_SIZE)
+                    .await
+                    .expect("Unable to export the accounts from the storage");
+            let transaction_hash: H256 =
+                sp_core_hashing::blake2_256(&(shard_move.source, shard_move.last_moved).encode())
+                    .into();
+
+            for (target_key, target) in &shard_move.targets {
+                let chunk: Vec<_> = accounts
+                    .iter()
+                    .filter(|account_data| target_key.contains(&account_data.account))
+                    .cloned()
+                    .collect();
+                if !chunk.is_empty() {
+                    import_accounts(target, transaction_hash, chunk)
+                        .await
+                        .expect("Unable to import the accounts to the storage");
+                }
+            }
+
+            if let (Some(shard_move), Some(account_data)) =
+                (self.shard_moves.get_mut(key), accounts.last())
+            {
+                shard_move.last_moved = Some(account_data.account);
+                shard_move.driven_until = exec::block_height() + MOVE_LEASE;
+            }
+            if (accounts.len() as u32) < MOVE_CHUNK_SIZE {
+                return self.check_no_transactions_in_progress(&shard_move.source);
+            }
+        }
+    }
+
+    /// The accounts can't be moved while there are transactions in progress
+    /// that may involve the storage: their storage messages might be applied again
+    /// by the new storages or their compensations rejected for the exported accounts.
+    /// The transactions without instructions are done with a single storage message
+    /// whose storage isn't recorded, so they're taken into account as well.
+    fn check_no_transactions_in_progress(&self, storage_id: &ActorId) -> Result<(), FTokenError> {
+        let in_progress = self
+            .transaction_status
+            .iter()
+            .filter(|(_, status)| matches!(status, TransactionStatus::InProgress))
+            .any(|(transaction_hash, _)| {
+                self.instructions
+                    .get(transaction_hash)
+                    .map_or(true, |instructions| {
+                        instructions
+                            .iter()
+                            .any(|instruction| instruction.address == *storage_id)
+                    })
+            });
+        if in_progress {
+            Err(FTokenError::TransactionsInProgress)
+        } else {
+            Ok(())
+        }
     }
 
-    fn get_storage_address(&mut self, address: &ActorId) -> ActorId {
-        let encoded = hex::encode(address.as_ref());
-        let id: String = encoded.chars().next().expect("Can't be None").to_string();
+    /// Returns the key of the shard that contains the account.
+    fn shard_of(&self, account: &ActorId) -> ShardKey {
+        let mut key = ShardKey::of(account, self.shard_bits);
+        while self.split_shards.contains(&key) {
+            key = ShardKey::of(account, key.bits + 1);
+        }
+        key
+    }
+
+    fn get_storage_address(&mut self, address: &ActorId) -> Result<ActorId, FTokenError> {
+        let id = self.shard_of(address);
         if let Some(address) = self.id_to_storage.get(&id) {
-            *address
+            Ok(*address)
         } else {
-            let (_message_id, address) = ProgramGenerator::create_program_with_gas(
-                self.storage_code_hash.into(),
-                "",
-                GAS_STORAGE_CREATION,
-                0,
-            )
-            .expect("Error in creating Storage program");
+            let address = self.create_storage()?;
             self.id_to_storage.insert(id, address);
-            address
+            Ok(address)
         }
     }
 
+    fn create_storage(&self) -> Result<ActorId, FTokenError> {
+        ProgramGenerator::create_program_with_gas(
+            self.storage_code_hash.into(),
+            "",
+            GAS_STORAGE_CREATION,
+            0,
+        )
+        .map(|(_message_id, address)| address)
+        .map_err(|_| FTokenError::StorageCreationFailed)
+    }
+
+    fn find_storage_address(&self, account: &ActorId) -> Option<&ActorId> {
+        self.id_to_storage.get(&self.shard_of(account))
+    }
+
     async fn get_permit_id(&self, account: &ActorId) {
-        let encoded = hex::encode(account.as_ref());
-        let id: String = encoded.chars().next().expect("Can't be None").to_string();
-        if let Some(address) = self.id_to_storage.get(&id) {
+        if let Some(address) = self.find_storage_address(account) {
             let permit_id = get_permit_id(address, account).await;
             msg::reply(FTLogicEvent::PermitId(permit_id), 0)
                 .expect("Error in a reply `FTLogicEvent::PermitId`");
@@ -368,20 +1184,30 @@ impl FTLogic {
         transaction_hash: H256,
         account: &ActorId,
         expected_id: &u128,
-    ) -> bool {
-        let encoded = hex::encode(account.as_ref());
-        let id: String = encoded.chars().next().expect("Can't be None").to_string();
-        if let Some(address) = self.id_to_storage.get(&id) {
+    ) -> Result<(), FTokenError> {
+        if let Some(address) = self.find_storage_address(account) {
             return check_and_increment_permit_id(address, transaction_hash, account, *expected_id)
                 .await;
         }
-        false
+        Err(FTokenError::WrongPermitId)
+    }
+
+    async fn use_permit_nonce(
+        &mut self,
+        transaction_hash: H256,
+        account: &ActorId,
+        nonce: u128,
+    ) -> Result<(), FTokenError> {
+        let address = self.get_storage_address(account)?;
+        // The approval is sent to the same storage under the transaction hash,
+        // so the nonce is used under its own one.
+        let nonce_transaction_hash: H256 =
+            sp_core_hashing::blake2_256(&(transaction_hash, nonce).encode()).into();
+        use_permit_nonce(nonce_transaction_hash, &address, account, nonce).await
     }
 
     async fn get_balance(&self, account: &ActorId) {
-        let encoded = hex::encode(account.as_ref());
-        let id: String = encoded.chars().next().expect("Can't be None").to_string();
-        if let Some(address) = self.id_to_storage.get(&id) {
+        if let Some(address) = self.find_storage_address(account) {
             let balance = get_balance(address, account).await;
             msg::reply(FTLogicEvent::Balance(balance), 0)
                 .expect("Error in a reply `FTLogicEvent::Balance`");
@@ -391,8 +1217,33 @@ impl FTLogic {
         }
     }
 
+    async fn get_allowance(&self, owner: &ActorId, spender: &ActorId) {
+        if let Some(address) = self.find_storage_address(owner) {
+            let allowance = get_allowance(address, owner, spender).await;
+            msg::reply(FTLogicEvent::Allowance(allowance), 0)
+                .expect("Error in a reply `FTLogicEvent::Allowance`");
+        } else {
+            msg::reply(FTLogicEvent::Allowance(0), 0)
+                .expect("Error in a reply `FTLogicEvent::Allowance`");
+        }
+    }
+
+    fn reply_has_role(&self, role: Role, account: &ActorId) {
+        msg::reply(FTLogicEvent::HasRole(self.has_role(role, account)), 0)
+            .expect("Error in a reply `FTLogicEvent::HasRole`");
+    }
+
+    fn get_total_supply(&self) {
+        msg::reply(FTLogicEvent::TotalSupply(self.total_supply), 0)
+            .expect("Error in a reply `FTLogicEvent::TotalSupply`");
+    }
+
     fn clear(&mut self, transaction_hash: H256) {
         self.transaction_status.remove(&transaction_hash);
+        self.instructions.remove(&transaction_hash);
+        self.pending_mints.remove(&transaction_hash);
+        self.minted.remove(&transaction_hash);
+        self.authorized_transactions.remove(&transaction_hash);
     }
 
     fn assert_main_contract(&self) {
@@ -402,14 +1253,6 @@ impl FTLogic {
             "Only main fungible token contract can send that message"
         );
     }
-
-    fn assert_admin(&self) {
-        assert_eq!(
-            self.admin,
-            msg::source(),
-            "Only admin can send that message"
-        );
-    }
 }
 
 #[gstd::async_main]
@@ -425,33 +1268,77 @@ async fn main() {
         FTLogicAction::UpdateStorageCodeHash(storage_code_hash) => {
             logic.update_storage_hash(storage_code_hash)
         }
-        FTLogicAction::Clear(transaction_hash) => logic.clear(transaction_hash),
+        FTLogicAction::Clear(transaction_hash) => {
+            assert_eq!(
+                msg::source(),
+                exec::program_id(),
+                "Only the contract itself can clear the transactions"
+            );
+            logic.clear(transaction_hash)
+        }
+        FTLogicAction::ClearTransactions(transaction_hashes) => {
+            assert_eq!(
+                msg::source(),
+                exec::program_id(),
+                "Only the contract itself can clear the transactions"
+            );
+            for transaction_hash in transaction_hashes {
+                logic.clear(transaction_hash);
+            }
+        }
         FTLogicAction::GetBalance(account) => logic.get_balance(&account).await,
         FTLogicAction::GetPermitId(account) => logic.get_permit_id(&account).await,
-        _ => {}
+        FTLogicAction::GetAllowance { owner, spender } => {
+            logic.get_allowance(&owner, &spender).await
+        }
+        FTLogicAction::MigrateStorages { new_ft_logic_id } => {
+            logic.migrate_storages(&new_ft_logic_id).await
+        }
+        FTLogicAction::ImportStorages(migration_data) => logic.import_storages(migration_data),
+        FTLogicAction::ExportMigrationData => logic.export_migration_data(),
+        FTLogicAction::Admin { account, action } => logic.admin_action(&account, action),
+        FTLogicAction::SetAdmin(admin) => logic.set_admin(&admin),
+        FTLogicAction::HasRole { role, account } => logic.reply_has_role(role, &account),
+        FTLogicAction::GetTotalSupply => logic.get_total_supply(),
+        FTLogicAction::SplitShard(key) => logic.split_shard(key).await,
+        FTLogicAction::UpgradeStorage(key) => logic.upgrade_storage(key).await,
     }
 }
 
 #[no_mangle]
 unsafe extern "C" fn init() {
     let init_config: InitFTLogic = msg::load().expect("Unable to decode `InitFTLogic`");
+    assert!(
+        (1..=MAX_SHARD_BITS).contains(&init_config.shard_bits),
+        "The number of shard bits must be in the range from 1 to {MAX_SHARD_BITS}"
+    );
     let ft_logic = FTLogic {
         admin: init_config.admin,
         storage_code_hash: init_config.storage_code_hash,
+        max_supply: init_config.max_supply,
+        shard_bits: init_config.shard_bits,
+        allowlist_enabled: init_config.allowlist,
+        multisig: init_config.multisig,
         ftoken_id: msg::source(),
         ..Default::default()
     };
     FT_LOGIC = Some(ft_logic);
 }
 
-fn reply_err() {
-    msg::reply(FTLogicEvent::Err, 0).expect("Error in sending a reply `FTLogicEvent::Err`");
+fn reply_err(error: FTokenError) {
+    msg::reply(FTLogicEvent::Err(error), 0).expect("Error in sending a reply `FTLogicEvent::Err`");
 }
 
 fn reply_ok() {
     msg::reply(FTLogicEvent::Ok, 0).expect("Error in sending a reply `FTLogicEvent::Ok`");
 }
 
+/// The hash of the compensation of a storage message,
+/// the storage has already recorded the hash of the message itself.
+fn compensation_hash(transaction_hash: H256) -> H256 {
+    sp_core_hashing::blake2_256(&(transaction_hash, "compensation").encode()).into()
+}
+
 fn send_delayed_clear(transaction_hash: H256) {
     msg::send_delayed(
         exec::program_id(),
@@ -479,10 +1366,24 @@ extern "C" fn state() {
             .map(|(key, value)| (*key, value.clone()))
             .collect(),
         storage_code_hash: logic.storage_code_hash,
+        shard_bits: logic.shard_bits,
         id_to_storage: logic
             .id_to_storage
             .iter()
-            .map(|(key, value)| (key.clone(), *value))
+            .map(|(key, value)| (*key, *value))
+            .collect(),
+        split_shards: logic.split_shards.iter().copied().collect(),
+        roles: logic.roles.clone(),
+        total_supply: logic.total_supply,
+        max_supply: logic.max_supply,
+        paused: logic.paused,
+        frozen: logic.frozen.iter().copied().collect(),
+        allowlist_enabled: logic.allowlist_enabled,
+        allowlist: logic.allowlist.iter().copied().collect(),
+        shard_moves: logic
+            .shard_moves
+            .iter()
+            .map(|(key, value)| (*key, value.clone()))
             .collect(),
     };
     msg::reply(logic_state, 0).expect("Failed to share state");
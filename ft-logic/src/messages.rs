@@ -1,120 +1,157 @@
 use crate::H256;
-use ft_storage_io::{FTStorageAction, FTStorageEvent};
-use gstd::{msg, ActorId};
+use ft_storage_io::{AccountData, FTStorageAction, FTStorageEvent, FTokenError};
+use gstd::{msg, prelude::*, ActorId};
 
-pub async fn increase_balance(
+pub async fn decrease_balance(
     transaction_hash: H256,
     storage_id: &ActorId,
+    msg_source: &ActorId,
     account: &ActorId,
     amount: u128,
-) -> Result<(), ()> {
+) -> Result<(), FTokenError> {
     let result = msg::send_for_reply_as::<_, FTStorageEvent>(
         *storage_id,
-        FTStorageAction::IncreaseBalance {
+        FTStorageAction::DecreaseBalance {
             transaction_hash,
+            msg_source: *msg_source,
             account: *account,
             amount,
         },
         0,
         0,
     )
-    .expect("Error in sending a message `FTStorageAction::IncreaseBalance`")
+    .expect("Error in sending a message `FTStorageAction::DecreaseBalance`")
     .await;
     match result {
-        Ok(storage_event) => match storage_event {
-            FTStorageEvent::Ok => Ok(()),
-            _ => Err(()),
-        },
-        Err(_) => Err(()),
+        Ok(FTStorageEvent::Ok) => Ok(()),
+        Ok(FTStorageEvent::Err(error)) => Err(error),
+        _ => Err(FTokenError::MessageFailed),
     }
 }
 
-pub async fn decrease_balance(
+/// The way `change_allowance` changes an allowance.
+#[derive(Clone, Copy)]
+pub enum AllowanceChange {
+    Set,
+    Increase,
+    Decrease,
+}
+
+pub async fn change_allowance(
     transaction_hash: H256,
     storage_id: &ActorId,
     msg_source: &ActorId,
     account: &ActorId,
     amount: u128,
-) -> Result<(), ()> {
+    change: AllowanceChange,
+) -> Result<(), FTokenError> {
+    let (msg_source, account) = (*msg_source, *account);
+    let action = match change {
+        AllowanceChange::Set => FTStorageAction::Approve {
+            transaction_hash,
+            msg_source,
+            account,
+            amount,
+        },
+        AllowanceChange::Increase => FTStorageAction::IncreaseAllowance {
+            transaction_hash,
+            msg_source,
+            account,
+            amount,
+        },
+        AllowanceChange::Decrease => FTStorageAction::DecreaseAllowance {
+            transaction_hash,
+            msg_source,
+            account,
+            amount,
+        },
+    };
+    let result = msg::send_for_reply_as::<_, FTStorageEvent>(*storage_id, action, 0, 0)
+        .expect("Error in sending a message that changes the allowance")
+        .await;
+    match result {
+        Ok(FTStorageEvent::Ok) => Ok(()),
+        Ok(FTStorageEvent::Err(error)) => Err(error),
+        _ => Err(FTokenError::MessageFailed),
+    }
+}
+
+pub async fn transfer(
+    transaction_hash: H256,
+    storage_id: &ActorId,
+    msg_source: &ActorId,
+    sender: &ActorId,
+    recipient: &ActorId,
+    amount: u128,
+) -> Result<(), FTokenError> {
     let result = msg::send_for_reply_as::<_, FTStorageEvent>(
         *storage_id,
-        FTStorageAction::DecreaseBalance {
+        FTStorageAction::Transfer {
             transaction_hash,
             msg_source: *msg_source,
-            account: *account,
+            sender: *sender,
+            recipient: *recipient,
             amount,
         },
         0,
         0,
     )
-    .expect("Error in sending a message `FTStorageAction::DecreaseBalance`")
+    .expect("Error in sending a message `FTStorageAction::Transfer`")
     .await;
     match result {
-        Ok(storage_event) => match storage_event {
-            FTStorageEvent::Ok => Ok(()),
-            _ => Err(()),
-        },
-        Err(_) => Err(()),
+        Ok(FTStorageEvent::Ok) => Ok(()),
+        Ok(FTStorageEvent::Err(error)) => Err(error),
+        _ => Err(FTokenError::MessageFailed),
     }
 }
 
-pub async fn approve(
+pub async fn use_permit_nonce(
     transaction_hash: H256,
     storage_id: &ActorId,
-    msg_source: &ActorId,
     account: &ActorId,
-    amount: u128,
-) -> Result<(), ()> {
+    nonce: u128,
+) -> Result<(), FTokenError> {
     let result = msg::send_for_reply_as::<_, FTStorageEvent>(
         *storage_id,
-        FTStorageAction::Approve {
+        FTStorageAction::UsePermitNonce {
             transaction_hash,
-            msg_source: *msg_source,
             account: *account,
-            amount,
+            nonce,
         },
         0,
         0,
     )
-    .expect("Error in sending a message `FTStorageAction::DecreaseBalance`")
+    .expect("Error in sending a message `FTStorageAction::UsePermitNonce`")
     .await;
     match result {
-        Ok(storage_event) => match storage_event {
-            FTStorageEvent::Ok => Ok(()),
-            _ => Err(()),
-        },
-        Err(_) => Err(()),
+        Ok(FTStorageEvent::Ok) => Ok(()),
+        Ok(FTStorageEvent::Err(error)) => Err(error),
+        _ => Err(FTokenError::MessageFailed),
     }
 }
 
-pub async fn transfer(
+pub async fn use_nonce(
     transaction_hash: H256,
     storage_id: &ActorId,
-    msg_source: &ActorId,
-    sender: &ActorId,
-    recipient: &ActorId,
-    amount: u128,
-) -> Result<(), ()> {
+    account: &ActorId,
+    nonce: H256,
+) -> Result<(), FTokenError> {
     let result = msg::send_for_reply_as::<_, FTStorageEvent>(
         *storage_id,
-        FTStorageAction::Transfer {
+        FTStorageAction::UseNonce {
             transaction_hash,
-            msg_source: *msg_source,
-            sender: *sender,
-            recipient: *recipient,
-            amount,
+            account: *account,
+            nonce,
         },
         0,
         0,
     )
-    .expect("Error in sending a message `FTStorageAction::Transfer`")
+    .expect("Error in sending a message `FTStorageAction::UseNonce`")
     .await;
     match result {
-        Ok(storage_event) => match storage_event {
-            FTStorageEvent::Ok => Ok(()),
-            _ => Err(()),
-        },
-        Err(_) => Err(()),
+        Ok(FTStorageEvent::Ok) => Ok(()),
+        Ok(FTStorageEvent::Err(error)) => Err(error),
+        _ => Err(FTokenError::MessageFailed),
     }
 }
 
@@ -140,8 +177,8 @@ pub async fn check_and_increment_permit_id(
     transaction_hash: H256,
     account: &ActorId,
     expected_permit_id: u128,
-) -> bool {
-    let reply = msg::send_for_reply_as::<_, FTStorageEvent>(
+) -> Result<(), FTokenError> {
+    let result = msg::send_for_reply_as::<_, FTStorageEvent>(
         *storage_id,
         FTStorageAction::IncrementPermitId {
             transaction_hash,
@@ -152,12 +189,32 @@ pub async fn check_and_increment_permit_id(
         0,
     )
     .expect("Error in sending a message `FTStorageAction::IncrementPermitId")
+    .await;
+    match result {
+        Ok(FTStorageEvent::Ok) => Ok(()),
+        Ok(FTStorageEvent::Err(error)) => Err(error),
+        _ => Err(FTokenError::MessageFailed),
+    }
+}
+
+pub async fn get_allowance(storage_id: &ActorId, owner: &ActorId, spender: &ActorId) -> u128 {
+    let reply = msg::send_for_reply_as::<_, FTStorageEvent>(
+        *storage_id,
+        FTStorageAction::GetAllowance {
+            owner: *owner,
+            spender: *spender,
+        },
+        0,
+        0,
+    )
+    .expect("Error in sending a message `FTStorageAction::GetAllowance")
     .await
     .expect("Unable to decode `FTStorageEvent");
-    if let FTStorageEvent::Ok = reply {
-        return true;
+    if let FTStorageEvent::Allowance(allowance) = reply {
+        allowance
+    } else {
+        0
     }
-    false
 }
 
 pub async fn get_balance(storage_id: &ActorId, account: &ActorId) -> u128 {
@@ -176,3 +233,59 @@ pub async fn get_balance(storage_id: &ActorId, account: &ActorId) -> u128 {
         0
     }
 }
+
+pub async fn update_logic_contract(storage_id: &ActorId, ft_logic_id: &ActorId) -> Result<(), ()> {
+    let result = msg::send_for_reply_as::<_, FTStorageEvent>(
+        *storage_id,
+        FTStorageAction::UpdateLogicContract(*ft_logic_id),
+        0,
+        0,
+    )
+    .expect("Error in sending a message `FTStorageAction::UpdateLogicContract`")
+    .await;
+    match result {
+        Ok(FTStorageEvent::Ok) => Ok(()),
+        _ => Err(()),
+    }
+}
+
+pub async fn export_accounts(
+    storage_id: &ActorId,
+    after: Option<ActorId>,
+    limit: u32,
+) -> Result<Vec<AccountData>, ()> {
+    let result = msg::send_for_reply_as::<_, FTStorageEvent>(
+        *storage_id,
+        FTStorageAction::ExportAccounts { after, limit },
+        0,
+        0,
+    )
+    .expect("Error in sending a message `FTStorageAction::ExportAccounts`")
+    .await;
+    match result {
+        Ok(FTStorageEvent::Accounts(accounts)) => Ok(accounts),
+        _ => Err(()),
+    }
+}
+
+pub async fn import_accounts(
+    storage_id: &ActorId,
+    transaction_hash: H256,
+    accounts: Vec<AccountData>,
+) -> Result<(), ()> {
+    let result = msg::send_for_reply_as::<_, FTStorageEvent>(
+        *storage_id,
+        FTStorageAction::ImportAccounts {
+            transaction_hash,
+            accounts,
+        },
+        0,
+        0,
+    )
+    .expect("Error in sending a message `FTStorageAction::ImportAccounts`")
+    .await;
+    match result {
+        Ok(FTStorageEvent::Ok) => Ok(()),
+        _ => Err(()),
+    }
+}
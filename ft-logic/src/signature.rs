@@ -0,0 +1,72 @@
+use ft_main_io::MultiSignature;
+use gstd::{prelude::*, ActorId};
+
+/// Verifies that the `message` is signed by the `signer`.
+///
+/// The address of an sr25519 or ed25519 signer is its public key. The address of a Substrate
+/// ECDSA signer is the blake2 hash of its compressed public key and the signatures
+/// are made over the blake2 hash of the message. The address of an Ethereum signer is
+/// the last 20 bytes of the keccak256 hash of its uncompressed public key padded with zeros
+/// on the left, the signatures are made over the keccak256 hash of the message
+/// with the EIP-191 prefix.
+pub fn verify(signature: &MultiSignature, message: &[u8], signer: &ActorId) -> bool {
+    match signature {
+        MultiSignature::Sr25519(signature) => {
+            light_sr25519::verify(signature.as_bytes(), message, signer).is_ok()
+        }
+        MultiSignature::Ed25519(signature) => verify_ed25519(signature.as_bytes(), message, signer),
+        MultiSignature::Ecdsa(signature) => verify_ecdsa(signature, message, signer),
+        MultiSignature::EthereumEcdsa(signature) => {
+            verify_ethereum_ecdsa(signature, message, signer)
+        }
+    }
+}
+
+fn verify_ed25519(signature: &[u8], message: &[u8], signer: &ActorId) -> bool {
+    use ed25519_dalek::{PublicKey, Signature, Verifier};
+
+    let Ok(public_key) = PublicKey::from_bytes(signer.as_ref()) else {
+        return false;
+    };
+    let Ok(signature) = Signature::from_bytes(signature) else {
+        return false;
+    };
+    public_key.verify(message, &signature).is_ok()
+}
+
+fn verify_ecdsa(signature: &[u8; 65], message: &[u8], signer: &ActorId) -> bool {
+    match recover(signature, sp_core_hashing::blake2_256(message)) {
+        Some(public_key) => {
+            sp_core_hashing::blake2_256(&public_key.serialize_compressed()) == *signer.as_ref()
+        }
+        None => false,
+    }
+}
+
+fn verify_ethereum_ecdsa(signature: &[u8; 65], message: &[u8], signer: &ActorId) -> bool {
+    let prefix = format!("\x19Ethereum Signed Message:\n{}", message.len());
+    let hash = sp_core_hashing::keccak_256(&[prefix.as_bytes(), message].concat());
+    match recover(signature, hash) {
+        Some(public_key) => {
+            let mut address = [0; 32];
+            address[12..]
+                .copy_from_slice(&sp_core_hashing::keccak_256(&public_key.serialize()[1..])[12..]);
+            address == *signer.as_ref()
+        }
+        None => false,
+    }
+}
+
+/// Recovers the public key from the signature of the message hash,
+/// the recovery id may be either 0 and 1 or 27 and 28 as Ethereum wallets set it.
+fn recover(signature: &[u8; 65], hash: [u8; 32]) -> Option<libsecp256k1::PublicKey> {
+    use libsecp256k1::{Message, RecoveryId, Signature};
+
+    let recovery_id = match signature[64] {
+        27 | 28 => signature[64] - 27,
+        recovery_id => recovery_id,
+    };
+    let recovery_id = RecoveryId::parse(recovery_id).ok()?;
+    let signature = Signature::parse_standard_slice(&signature[..64]).ok()?;
+    libsecp256k1::recover(&Message::parse(&hash), &signature, &recovery_id).ok()
+}
@@ -2,6 +2,14 @@
 use gmeta::{In, InOut, Metadata};
 use gstd::{prelude::*, ActorId};
 use primitive_types::{H256, H512};
+
+mod multisig;
+mod roles;
+mod timelock;
+pub use multisig::*;
+pub use roles::*;
+pub use timelock::*;
+
 pub struct FMainTokenMetadata;
 
 impl Metadata for FMainTokenMetadata {
@@ -15,9 +23,19 @@ impl Metadata for FMainTokenMetadata {
 
 #[derive(Default, Encode, Decode, TypeInfo, Debug)]
 pub struct FTokenState {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
     pub admin: ActorId,
+    pub pending_admin: Option<ActorId>,
     pub ft_logic_id: ActorId,
+    pub pending_ft_logic_id: Option<ActorId>,
     pub transactions: Vec<(H256, TransactionStatus)>,
+    pub signers: Vec<ActorId>,
+    pub threshold: u32,
+    pub proposals: Vec<(H256, Proposal)>,
+    pub upgrade_delay: u32,
+    pub pending_upgrades: Vec<(H256, PendingUpgrade)>,
 }
 
 #[derive(Encode, Decode, TypeInfo, Debug)]
@@ -34,6 +52,58 @@ pub enum FTokenAction {
     GetPermitId(ActorId),
     Clear(H256),
     MigrateStorageAddresses,
+    /// Grants the `Role::Minter`.
+    AddMinter(ActorId),
+    /// Revokes the `Role::Minter`.
+    RemoveMinter(ActorId),
+    GetName,
+    GetSymbol,
+    GetDecimals,
+    GetTotalSupply,
+    GetAllowance {
+        owner: ActorId,
+        spender: ActorId,
+    },
+    /// Makes all the `Message` actions fail until `Unpause`, the queries keep working.
+    Pause,
+    Unpause,
+    /// Freezes the account: it can't send, receive or approve the tokens
+    /// and can't be a permit owner.
+    Freeze(ActorId),
+    Unfreeze(ActorId),
+    AddToAllowlist(ActorId),
+    RemoveFromAllowlist(ActorId),
+    /// Proposes the new admin, who becomes the admin after sending `AcceptAdmin`.
+    ProposeAdmin(ActorId),
+    AcceptAdmin,
+    GrantRole {
+        role: Role,
+        account: ActorId,
+    },
+    RevokeRole {
+        role: Role,
+        account: ActorId,
+    },
+    /// Removes the role from the sender.
+    RenounceRole(Role),
+    HasRole {
+        role: Role,
+        account: ActorId,
+    },
+    /// Proposes the privileged action to the signers, replies with the id of the proposal.
+    Propose(PrivilegedAction),
+    /// Confirms the proposal, the action is executed once the threshold is reached.
+    Confirm(H256),
+    /// Withdraws the confirmation of the sender.
+    RevokeConfirmation(H256),
+    UpdateStorageCodeHash(H256),
+    /// Queues the upgrade, replies with its id.
+    ScheduleUpgrade(Upgrade),
+    /// Cancels the upgrade before it's executed.
+    CancelUpgrade(H256),
+    /// Sent by the main contract itself once the delay of the upgrade passes.
+    /// If the upgrade fails, it's kept and can be executed again by the signers or the upgraders.
+    ExecuteUpgrade(H256),
 }
 
 #[derive(Encode, Decode, TypeInfo, Debug)]
@@ -47,9 +117,55 @@ pub enum FTokenInnerAction {
     GetPermitId(ActorId),
     Clear(H256),
     MigrateStorageAddresses,
+    AddMinter(ActorId),
+    RemoveMinter(ActorId),
+    GetName,
+    GetSymbol,
+    GetDecimals,
+    GetTotalSupply,
+    GetAllowance {
+        owner: ActorId,
+        spender: ActorId,
+    },
+    Pause,
+    Unpause,
+    Freeze(ActorId),
+    Unfreeze(ActorId),
+    AddToAllowlist(ActorId),
+    RemoveFromAllowlist(ActorId),
+    ProposeAdmin(ActorId),
+    AcceptAdmin,
+    GrantRole {
+        role: Role,
+        account: ActorId,
+    },
+    RevokeRole {
+        role: Role,
+        account: ActorId,
+    },
+    /// Removes the role from the sender.
+    RenounceRole(Role),
+    HasRole {
+        role: Role,
+        account: ActorId,
+    },
+    /// Proposes the privileged action to the signers, replies with the id of the proposal.
+    Propose(PrivilegedAction),
+    /// Confirms the proposal, the action is executed once the threshold is reached.
+    Confirm(H256),
+    /// Withdraws the confirmation of the sender.
+    RevokeConfirmation(H256),
+    UpdateStorageCodeHash(H256),
+    /// Queues the upgrade, replies with its id.
+    ScheduleUpgrade(Upgrade),
+    /// Cancels the upgrade before it's executed.
+    CancelUpgrade(H256),
+    /// Sent by the main contract itself once the delay of the upgrade passes.
+    /// If the upgrade fails, it's kept and can be executed again by the signers or the upgraders.
+    ExecuteUpgrade(H256),
 }
 
-#[derive(Encode, Debug, Decode, TypeInfo, Copy, Clone)]
+#[derive(Encode, Debug, Decode, TypeInfo, Clone)]
 pub enum LogicAction {
     Mint {
         recipient: ActorId,
@@ -64,36 +180,156 @@ pub enum LogicAction {
         recipient: ActorId,
         amount: u128,
     },
+    /// Sets the amount the `approved_account` is allowed to transfer on behalf of the sender,
+    /// zero revokes the approval.
     Approve {
         approved_account: ActorId,
         amount: u128,
     },
+    IncreaseAllowance {
+        approved_account: ActorId,
+        amount: u128,
+    },
+    DecreaseAllowance {
+        approved_account: ActorId,
+        amount: u128,
+    },
     Permit {
         owner_account: ActorId,
         approved_account: ActorId,
         amount: u128,
-        permit_id: u128,
-        sign: H512,
+        permit_id: PermitNonce,
+        /// The last block timestamp (in milliseconds) at which the permit is valid.
+        deadline: u64,
+        sign: MultiSignature,
+    },
+    /// Transfers the tokens of the `sender` who signed the authorization,
+    /// so anyone can submit it. The authorization is valid after the `valid_after`
+    /// and before the `valid_before` block timestamps (in milliseconds),
+    /// its `nonce` is used once even if the transfer fails.
+    TransferWithAuthorization {
+        sender: ActorId,
+        recipient: ActorId,
+        amount: u128,
+        valid_after: u64,
+        valid_before: u64,
+        nonce: H256,
+        sign: MultiSignature,
+    },
+    /// Transfers the tokens from the sender to each of the recipients,
+    /// either all the transfers are done or none of them.
+    BatchTransfer {
+        sender: ActorId,
+        transfers: Vec<(ActorId, u128)>,
     },
+    /// Mints the tokens to each of the recipients,
+    /// either all the mints are done or none of them.
+    BatchMint(Vec<(ActorId, u128)>),
 }
 
-#[derive(Debug, Encode, Decode, TypeInfo, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[derive(Debug, Encode, Decode, TypeInfo, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub enum FTokenEvent {
     Ok,
-    Err,
+    Err(FTokenError),
     Balance(u128),
     PermitId(u128),
+    Name(String),
+    Symbol(String),
+    Decimals(u8),
+    TotalSupply(u128),
+    Allowance(u128),
+    HasRole(bool),
+    Proposed(H256),
+    UpgradeScheduled(H256),
+}
+
+/// A signature of one of the supported schemes.
+#[derive(Debug, Encode, Decode, TypeInfo, PartialEq, Eq, Clone, Copy)]
+pub enum MultiSignature {
+    Sr25519(H512),
+    Ed25519(H512),
+    /// A recoverable secp256k1 signature of a Substrate account: `r`, `s` and the recovery id.
+    Ecdsa([u8; 65]),
+    /// A recoverable secp256k1 signature of an Ethereum account made with `personal_sign`:
+    /// `r`, `s` and `v`.
+    EthereumEcdsa([u8; 65]),
+}
+
+/// The nonce of a permit.
+#[derive(Debug, Encode, Decode, TypeInfo, PartialEq, Eq, Clone, Copy)]
+pub enum PermitNonce {
+    /// Must be equal to the current permit id of the owner, which is incremented then,
+    /// so the permits are used strictly in sequence.
+    Sequential(u128),
+    /// Any nonce that hasn't been used by the owner before,
+    /// so several permits can be signed and used in any order.
+    Unordered(u128),
+}
+
+/// The reason why a transaction has failed.
+#[derive(Debug, Encode, Decode, TypeInfo, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum FTokenError {
+    InsufficientBalance,
+    InsufficientAllowance,
+    InvalidSignature,
+    WrongPermitId,
+    StorageCreationFailed,
+    /// The storage is locked while its accounts are being moved to other storages.
+    StorageLocked,
+    NotMinter,
+    MaxSupplyExceeded,
+    /// A message to another contract of the token has failed or its reply can't be decoded.
+    MessageFailed,
+    PermitExpired,
+    /// The transfer authorization isn't valid yet or has expired.
+    AuthorizationNotValid,
+    NonceUsed,
+    /// The token is in the allowlist mode and the account isn't in the allowlist.
+    NotAllowlisted,
+    /// The token is paused by the admin.
+    Paused,
+    AccountFrozen,
+    /// The accounts of a storage can't be moved while there are transactions in progress
+    /// that may involve it, and the logic contract can't be migrated
+    /// while any transaction is in progress.
+    TransactionsInProgress,
+    /// The transaction has failed and the storage messages it has already done
+    /// couldn't be compensated.
+    CompensationFailed,
+    /// The account doesn't have the role the action requires.
+    MissingRole,
+    /// The sum of the amounts of a batch action overflows.
+    AmountOverflow,
 }
 
 #[derive(Encode, Decode, TypeInfo)]
 pub struct InitFToken {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
     pub storage_code_hash: H256,
     pub ft_logic_code_hash: H256,
+    pub max_supply: Option<u128>,
+    /// The number of the leading address bits that select the storage of an account,
+    /// there are `2^shard_bits` storages.
+    pub shard_bits: u8,
+    /// Only the accounts added with `AddToAllowlist` may hold or receive the tokens.
+    pub allowlist: bool,
+    /// The accounts that confirm the `PrivilegedAction`s.
+    /// If there are no signers, the privileged actions are sent directly.
+    /// Otherwise the admin has no implicit roles, the roles are only granted by the signers.
+    pub signers: Vec<ActorId>,
+    /// The number of the confirmations a proposal needs to be executed.
+    pub threshold: u32,
+    /// The number of blocks between scheduling an upgrade and its execution.
+    /// If it isn't zero, `UpdateLogicContract` and `UpdateStorageCodeHash`
+    /// can only be done with `ScheduleUpgrade`.
+    pub upgrade_delay: u32,
 }
 
 #[derive(Encode, Decode, TypeInfo, Copy, Clone, Debug)]
 pub enum TransactionStatus {
     InProgress,
     Success,
-    Failure,
+    Failure(FTokenError),
 }
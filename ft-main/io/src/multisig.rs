@@ -0,0 +1,39 @@
+use crate::{Role, Upgrade};
+use gstd::{prelude::*, ActorId};
+use primitive_types::H256;
+
+/// An action that is executed by the main contract once enough signers confirm it.
+/// The pause, freeze and allowlist actions can also be sent directly
+/// by the accounts the signers have granted the roles they require.
+#[derive(Debug, Encode, Decode, TypeInfo, PartialEq, Eq, Clone)]
+pub enum PrivilegedAction {
+    UpdateLogicContract {
+        ft_logic_code_hash: H256,
+        storage_code_hash: H256,
+    },
+    MigrateStorageAddresses,
+    UpdateStorageCodeHash(H256),
+    ScheduleUpgrade(Upgrade),
+    ProposeAdmin(ActorId),
+    GrantRole {
+        role: Role,
+        account: ActorId,
+    },
+    RevokeRole {
+        role: Role,
+        account: ActorId,
+    },
+    Pause,
+    Unpause,
+    Freeze(ActorId),
+    Unfreeze(ActorId),
+    AddToAllowlist(ActorId),
+    RemoveFromAllowlist(ActorId),
+}
+
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
+pub struct Proposal {
+    pub action: PrivilegedAction,
+    /// The signers that have confirmed the proposal.
+    pub confirmations: Vec<ActorId>,
+}
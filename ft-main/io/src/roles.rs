@@ -0,0 +1,42 @@
+use gstd::{prelude::*, ActorId};
+
+/// A set of privileged actions that can be granted to an account.
+/// The admin of the token has all the roles except `Burner`.
+#[derive(Debug, Encode, Decode, TypeInfo, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Role {
+    /// Grants and revokes the roles.
+    Admin,
+    /// Mints the tokens.
+    Minter,
+    /// Burns the tokens of any account without an allowance.
+    /// Unlike the other roles, the admin doesn't have it unless it's granted explicitly.
+    Burner,
+    /// Pauses and unpauses the token.
+    Pauser,
+    /// Freezes the accounts and manages the allowlist.
+    Compliance,
+    /// Upgrades the logic contract and the storages, splits the shards.
+    Upgrader,
+}
+
+/// The accounts that have been granted each role.
+#[derive(Debug, Default, Encode, Decode, TypeInfo, Clone)]
+pub struct Roles(BTreeMap<Role, BTreeSet<ActorId>>);
+
+impl Roles {
+    pub fn has_role(&self, role: Role, account: &ActorId) -> bool {
+        self.0
+            .get(&role)
+            .map_or(false, |accounts| accounts.contains(account))
+    }
+
+    pub fn grant(&mut self, role: Role, account: ActorId) {
+        self.0.entry(role).or_default().insert(account);
+    }
+
+    pub fn revoke(&mut self, role: Role, account: &ActorId) {
+        if let Some(accounts) = self.0.get_mut(&role) {
+            accounts.remove(account);
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use gstd::prelude::*;
+use primitive_types::H256;
+
+/// An upgrade that is executed after the `upgrade_delay` set in `InitFToken`,
+/// so the token holders have time to react to it.
+#[derive(Debug, Encode, Decode, TypeInfo, PartialEq, Eq, Clone)]
+pub enum Upgrade {
+    UpdateLogicContract {
+        ft_logic_code_hash: H256,
+        storage_code_hash: H256,
+    },
+    UpdateStorageCodeHash(H256),
+}
+
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
+pub struct PendingUpgrade {
+    pub upgrade: Upgrade,
+    /// The block height after which the upgrade is executed.
+    pub executable_at: u32,
+}
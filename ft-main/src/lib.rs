@@ -1,5 +1,5 @@
 #![no_std]
-use ft_logic_io::{FTLogicAction, FTLogicEvent, InitFTLogic};
+use ft_logic_io::{AdminAction, FTLogicAction, FTLogicEvent, InitFTLogic, MAX_SHARD_BITS};
 use ft_main_io::*;
 use gstd::{exec, msg, prelude::*, prog::ProgramGenerator, ActorId};
 use hashbrown::HashMap;
@@ -9,9 +9,22 @@ const DELAY: u32 = 600_000;
 
 #[derive(Default)]
 struct FToken {
+    name: String,
+    symbol: String,
+    decimals: u8,
     admin: ActorId,
+    pending_admin: Option<ActorId>,
     ft_logic_id: ActorId,
+    pending_ft_logic_id: Option<ActorId>,
+    migration_in_progress: bool,
     transactions: HashMap<H256, TransactionStatus>,
+    signers: Vec<ActorId>,
+    threshold: u32,
+    proposals: HashMap<H256, Proposal>,
+    proposal_nonce: u64,
+    upgrade_delay: u32,
+    pending_upgrades: HashMap<H256, PendingUpgrade>,
+    upgrade_nonce: u64,
 }
 
 static mut FTOKEN: Option<FToken> = None;
@@ -23,6 +36,10 @@ impl FToken {
     /// * `transaction_id`: the id of the transaction indicated by the actor that has sent that message;
     /// * `payload`: the message payload that will be sent to the logic token contract
     async fn message(&mut self, transaction_id: u64, payload: &[u8]) {
+        assert!(
+            !self.migration_in_progress,
+            "The storages are being migrated to the new logic contract"
+        );
         // Get the transaction hash from `msg::source` and `transaction_id`
         // Tracking the trandaction ids is a responsibility of the account or programs that sent that transaction.
         let transaction_hash = get_hash(&msg::source(), transaction_id);
@@ -47,8 +64,8 @@ impl FToken {
                 TransactionStatus::Success => {
                     reply_ok();
                 }
-                TransactionStatus::Failure => {
-                    reply_err();
+                TransactionStatus::Failure(error) => {
+                    reply_err(*error);
                 }
             },
         }
@@ -63,15 +80,21 @@ impl FToken {
                     .insert(transaction_hash, TransactionStatus::Success);
                 reply_ok();
             }
-            Err(()) => {
+            // The transaction stays in progress, so the resent one resumes the compensation.
+            Err(FTokenError::CompensationFailed) => reply_err(FTokenError::CompensationFailed),
+            Err(error) => {
                 self.transactions
-                    .insert(transaction_hash, TransactionStatus::Failure);
-                reply_err();
+                    .insert(transaction_hash, TransactionStatus::Failure(error));
+                reply_err(error);
             }
         };
     }
 
-    async fn send_message(&self, transaction_hash: H256, payload: &[u8]) -> Result<(), ()> {
+    async fn send_message(
+        &self,
+        transaction_hash: H256,
+        payload: &[u8],
+    ) -> Result<(), FTokenError> {
         let result = msg::send_for_reply_as::<_, FTLogicEvent>(
             self.ft_logic_id,
             FTLogicAction::Message {
@@ -86,7 +109,8 @@ impl FToken {
         .await;
         match result {
             Ok(FTLogicEvent::Ok) => Ok(()),
-            _ => Err(()),
+            Ok(FTLogicEvent::Err(error)) => Err(error),
+            _ => Err(FTokenError::MessageFailed),
         }
     }
 
@@ -122,19 +146,475 @@ impl FToken {
         }
     }
 
+    async fn get_allowance(&self, owner: &ActorId, spender: &ActorId) {
+        let reply = msg::send_for_reply_as::<_, FTLogicEvent>(
+            self.ft_logic_id,
+            FTLogicAction::GetAllowance {
+                owner: *owner,
+                spender: *spender,
+            },
+            0,
+            0,
+        )
+        .expect("Error in sending a message `FTLogicAction::GetAllowance")
+        .await
+        .expect("Unable to decode `FTLogicEvent");
+        if let FTLogicEvent::Allowance(allowance) = reply {
+            msg::reply(FTokenEvent::Allowance(allowance), 0)
+                .expect("Error in a reply `FTokenEvent::Allowance`");
+        }
+    }
+
+    async fn get_total_supply(&self) {
+        let reply = msg::send_for_reply_as::<_, FTLogicEvent>(
+            self.ft_logic_id,
+            FTLogicAction::GetTotalSupply,
+            0,
+            0,
+        )
+        .expect("Error in sending a message `FTLogicAction::GetTotalSupply")
+        .await
+        .expect("Unable to decode `FTLogicEvent");
+        if let FTLogicEvent::TotalSupply(total_supply) = reply {
+            msg::reply(FTokenEvent::TotalSupply(total_supply), 0)
+                .expect("Error in a reply `FTokenEvent::TotalSupply`");
+        }
+    }
+
+    async fn reply_has_role(&self, role: Role, account: &ActorId) {
+        let has_role = self.has_role(role, account).await;
+        msg::reply(FTokenEvent::HasRole(has_role), 0)
+            .expect("Error in a reply `FTokenEvent::HasRole`");
+    }
+
+    /// Creates a new logic contract. The current logic contract keeps serving the messages
+    /// until the storages are migrated with `MigrateStorageAddresses`.
     fn update_logic_contract(&mut self, ft_logic_code_hash: H256, storage_code_hash: H256) {
-        self.assert_admin();
+        match self.create_logic_contract(ft_logic_code_hash, storage_code_hash) {
+            Ok(()) => reply_ok(),
+            Err(error) => reply_err(error),
+        }
+    }
+
+    fn create_logic_contract(
+        &mut self,
+        ft_logic_code_hash: H256,
+        storage_code_hash: H256,
+    ) -> Result<(), FTokenError> {
+        assert!(
+            !self.migration_in_progress,
+            "The storages are being migrated to the new logic contract"
+        );
         let (_message_id, ft_logic_id) = ProgramGenerator::create_program(
             ft_logic_code_hash.into(),
             InitFTLogic {
-                admin: msg::source(),
+                admin: self.admin,
                 storage_code_hash,
+                // the maximum supply, the shards and the allowlist mode are taken
+                // from the current logic contract during the migration
+                max_supply: None,
+                shard_bits: MAX_SHARD_BITS,
+                allowlist: false,
+                multisig: !self.signers.is_empty(),
             }
             .encode(),
             0,
         )
-        .expect("Error in creating FToken Logic program");
-        self.ft_logic_id = ft_logic_id;
+        .map_err(|_| FTokenError::MessageFailed)?;
+        self.pending_ft_logic_id = Some(ft_logic_id);
+        Ok(())
+    }
+
+    /// Moves the storages and in-flight transactions from the current logic contract
+    /// to the one created by `UpdateLogicContract` and switches to the new logic contract.
+    /// Incoming messages are rejected until the migration is finished.
+    /// If the migration fails, the current logic contract is kept
+    /// and the migration can be sent again.
+    async fn migrate_storage_addresses(&mut self) {
+        let new_ft_logic_id = self
+            .pending_ft_logic_id
+            .expect("There is no logic contract to migrate to");
+        self.migration_in_progress = true;
+
+        let result = self.hand_over_storages(new_ft_logic_id).await;
+
+        self.migration_in_progress = false;
+        match result {
+            Ok(()) => {
+                self.ft_logic_id = new_ft_logic_id;
+                self.pending_ft_logic_id = None;
+                reply_ok();
+            }
+            Err(error) => reply_err(error),
+        }
+    }
+
+    /// The new logic contract imports the state of the current one
+    /// and only then the storages are handed over to it,
+    /// so the current logic contract keeps the storages if the import fails.
+    async fn hand_over_storages(&self, new_ft_logic_id: ActorId) -> Result<(), FTokenError> {
+        let reply = msg::send_for_reply_as::<_, FTLogicEvent>(
+            self.ft_logic_id,
+            FTLogicAction::ExportMigrationData,
+            0,
+            0,
+        )
+        .map_err(|_| FTokenError::MessageFailed)?
+        .await;
+        let migration_data = match reply {
+            Ok(FTLogicEvent::MigrationData(migration_data)) => migration_data,
+            Ok(FTLogicEvent::Err(error)) => return Err(error),
+            _ => return Err(FTokenError::MessageFailed),
+        };
+
+        let reply = msg::send_for_reply_as::<_, FTLogicEvent>(
+            new_ft_logic_id,
+            FTLogicAction::ImportStorages(migration_data),
+            0,
+            0,
+        )
+        .map_err(|_| FTokenError::MessageFailed)?
+        .await;
+        if !matches!(reply, Ok(FTLogicEvent::Ok)) {
+            return Err(FTokenError::MessageFailed);
+        }
+
+        let reply = msg::send_for_reply_as::<_, FTLogicEvent>(
+            self.ft_logic_id,
+            FTLogicAction::MigrateStorages { new_ft_logic_id },
+            0,
+            0,
+        )
+        .map_err(|_| FTokenError::MessageFailed)?
+        .await;
+        match reply {
+            Ok(FTLogicEvent::Ok) => Ok(()),
+            _ => Err(FTokenError::MessageFailed),
+        }
+    }
+
+    /// Forwards the action to the logic contract,
+    /// which checks that the account has the role the action requires.
+    async fn admin_action(&self, account: ActorId, action: AdminAction) {
+        self.forward_to_logic(FTLogicAction::Admin { account, action })
+            .await;
+    }
+
+    /// The privileged action sent directly by an account.
+    /// If the token has signers, only the pause, freeze and allowlist actions
+    /// can be sent directly by the accounts the signers have granted the roles,
+    /// the other actions must be proposed to the signers.
+    async fn privileged_action(&mut self, action: PrivilegedAction) {
+        let checked_by_logic = matches!(
+            action,
+            PrivilegedAction::Pause
+                | PrivilegedAction::Unpause
+                | PrivilegedAction::Freeze(_)
+                | PrivilegedAction::Unfreeze(_)
+                | PrivilegedAction::AddToAllowlist(_)
+                | PrivilegedAction::RemoveFromAllowlist(_)
+        );
+        assert!(
+            self.signers.is_empty() || checked_by_logic,
+            "The action must be proposed to the signers"
+        );
+        self.assert_not_timelocked(&action);
+        match &action {
+            PrivilegedAction::UpdateLogicContract { .. }
+            | PrivilegedAction::MigrateStorageAddresses
+            | PrivilegedAction::UpdateStorageCodeHash(_)
+            | PrivilegedAction::ScheduleUpgrade(_) => self.assert_role(Role::Upgrader).await,
+            PrivilegedAction::ProposeAdmin(_) => self.assert_admin(),
+            // the roles of the sender are checked by the logic contract
+            PrivilegedAction::GrantRole { .. }
+            | PrivilegedAction::RevokeRole { .. }
+            | PrivilegedAction::Pause
+            | PrivilegedAction::Unpause
+            | PrivilegedAction::Freeze(_)
+            | PrivilegedAction::Unfreeze(_)
+            | PrivilegedAction::AddToAllowlist(_)
+            | PrivilegedAction::RemoveFromAllowlist(_) => {}
+        }
+        self.execute(action, msg::source()).await;
+    }
+
+    /// Proposes the privileged action to the signers.
+    /// The id of the proposal is derived from the action and a nonce,
+    /// so the same action can be proposed again.
+    fn propose(&mut self, action: PrivilegedAction) {
+        self.assert_signer();
+        self.assert_not_timelocked(&action);
+        let proposal_id: H256 =
+            sp_core_hashing::blake2_256(&(&action, self.proposal_nonce).encode()).into();
+        self.proposal_nonce += 1;
+        self.proposals.insert(
+            proposal_id,
+            Proposal {
+                action,
+                confirmations: Vec::new(),
+            },
+        );
+        msg::reply(FTokenEvent::Proposed(proposal_id), 0)
+            .expect("Error in a reply `FTokenEvent::Proposed`");
+    }
+
+    /// Confirms the proposal and executes its action once the threshold is reached.
+    /// The action is executed on behalf of the main contract,
+    /// which has all the roles in the logic contract if the token has signers.
+    async fn confirm(&mut self, proposal_id: H256) {
+        self.assert_signer();
+        let signer = msg::source();
+        let proposal = self
+            .proposals
+            .get_mut(&proposal_id)
+            .expect("The proposal doesn't exist");
+        assert!(
+            !proposal.confirmations.contains(&signer),
+            "The proposal has already been confirmed by the signer"
+        );
+        proposal.confirmations.push(signer);
+        if proposal.confirmations.len() < self.threshold as usize {
+            reply_ok();
+            return;
+        }
+        let proposal = self
+            .proposals
+            .remove(&proposal_id)
+            .expect("The proposal doesn't exist");
+        self.execute(proposal.action, exec::program_id()).await;
+    }
+
+    fn revoke_confirmation(&mut self, proposal_id: H256) {
+        self.assert_signer();
+        let signer = msg::source();
+        let proposal = self
+            .proposals
+            .get_mut(&proposal_id)
+            .expect("The proposal doesn't exist");
+        proposal.confirmations.retain(|account| *account != signer);
+        reply_ok();
+    }
+
+    /// Executes the privileged action.
+    ///
+    /// Arguments:
+    /// * `action`: the action to execute;
+    /// * `account`: the account whose roles are checked by the logic contract.
+    async fn execute(&mut self, action: PrivilegedAction, account: ActorId) {
+        match action {
+            PrivilegedAction::UpdateLogicContract {
+                ft_logic_code_hash,
+                storage_code_hash,
+            } => self.update_logic_contract(ft_logic_code_hash, storage_code_hash),
+            PrivilegedAction::MigrateStorageAddresses => self.migrate_storage_addresses().await,
+            PrivilegedAction::UpdateStorageCodeHash(storage_code_hash) => {
+                self.forward_to_logic(FTLogicAction::UpdateStorageCodeHash(storage_code_hash))
+                    .await
+            }
+            PrivilegedAction::ScheduleUpgrade(upgrade) => self.schedule_upgrade(upgrade),
+            PrivilegedAction::ProposeAdmin(admin) => self.propose_admin(&admin),
+            PrivilegedAction::GrantRole {
+                role,
+                account: grantee,
+            } => {
+                self.admin_action(
+                    account,
+                    AdminAction::GrantRole {
+                        role,
+                        account: grantee,
+                    },
+                )
+                .await
+            }
+            PrivilegedAction::RevokeRole {
+                role,
+                account: grantee,
+            } => {
+                self.admin_action(
+                    account,
+                    AdminAction::RevokeRole {
+                        role,
+                        account: grantee,
+                    },
+                )
+                .await
+            }
+            PrivilegedAction::Pause => self.admin_action(account, AdminAction::Pause).await,
+            PrivilegedAction::Unpause => self.admin_action(account, AdminAction::Unpause).await,
+            PrivilegedAction::Freeze(frozen) => {
+                self.admin_action(account, AdminAction::Freeze(frozen))
+                    .await
+            }
+            PrivilegedAction::Unfreeze(frozen) => {
+                self.admin_action(account, AdminAction::Unfreeze(frozen))
+                    .await
+            }
+            PrivilegedAction::AddToAllowlist(allowed) => {
+                self.admin_action(account, AdminAction::AddToAllowlist(allowed))
+                    .await
+            }
+            PrivilegedAction::RemoveFromAllowlist(allowed) => {
+                self.admin_action(account, AdminAction::RemoveFromAllowlist(allowed))
+                    .await
+            }
+        }
+    }
+
+    /// The upgrades are executed only through `ScheduleUpgrade` if the upgrade delay is set.
+    fn assert_not_timelocked(&self, action: &PrivilegedAction) {
+        if self.upgrade_delay > 0 {
+            assert!(
+                !matches!(
+                    action,
+                    PrivilegedAction::UpdateLogicContract { .. }
+                        | PrivilegedAction::UpdateStorageCodeHash(_)
+                ),
+                "The upgrade must be scheduled"
+            );
+        }
+    }
+
+    /// Queues the upgrade and sends a delayed `ExecuteUpgrade` message to the main contract,
+    /// so the upgrade is executed once the upgrade delay passes unless it's cancelled.
+    fn schedule_upgrade(&mut self, upgrade: Upgrade) {
+        let upgrade_id: H256 =
+            sp_core_hashing::blake2_256(&(&upgrade, self.upgrade_nonce).encode()).into();
+        self.upgrade_nonce += 1;
+        self.pending_upgrades.insert(
+            upgrade_id,
+            PendingUpgrade {
+                upgrade,
+                executable_at: exec::block_height() + self.upgrade_delay,
+            },
+        );
+        msg::send_delayed(
+            exec::program_id(),
+            FTokenAction::ExecuteUpgrade(upgrade_id),
+            0,
+            self.upgrade_delay,
+        )
+        .expect("Error in sending a delayed message `FTokenAction::ExecuteUpgrade`");
+        msg::reply(FTokenEvent::UpgradeScheduled(upgrade_id), 0)
+            .expect("Error in a reply `FTokenEvent::UpgradeScheduled`");
+    }
+
+    /// Cancels the scheduled upgrade, it can be done by the signers and the upgraders.
+    async fn cancel_upgrade(&mut self, upgrade_id: H256) {
+        if !self.signers.contains(&msg::source()) {
+            self.assert_role(Role::Upgrader).await;
+        }
+        self.pending_upgrades
+            .remove(&upgrade_id)
+            .expect("The upgrade doesn't exist");
+        reply_ok();
+    }
+
+    /// Executes the upgrade once its delay passes. It's sent by the main contract itself,
+    /// if the execution fails, the upgrade is kept and can be executed again
+    /// by the signers or the upgraders.
+    async fn execute_upgrade(&mut self, upgrade_id: H256) {
+        let source = msg::source();
+        if source != exec::program_id() && !self.signers.contains(&source) {
+            self.assert_role(Role::Upgrader).await;
+        }
+        let Some(pending_upgrade) = self.pending_upgrades.get(&upgrade_id).cloned() else {
+            // the upgrade has been cancelled
+            assert_eq!(source, exec::program_id(), "The upgrade doesn't exist");
+            return;
+        };
+        assert!(
+            source == exec::program_id() || exec::block_height() >= pending_upgrade.executable_at,
+            "The upgrade delay hasn't passed yet"
+        );
+        let result = match pending_upgrade.upgrade {
+            Upgrade::UpdateLogicContract {
+                ft_logic_code_hash,
+                storage_code_hash,
+            } => self.create_logic_contract(ft_logic_code_hash, storage_code_hash),
+            Upgrade::UpdateStorageCodeHash(storage_code_hash) => {
+                self.send_to_logic(FTLogicAction::UpdateStorageCodeHash(storage_code_hash))
+                    .await
+            }
+        };
+        match result {
+            Ok(()) => {
+                self.pending_upgrades.remove(&upgrade_id);
+                reply_ok();
+            }
+            Err(error) => reply_err(error),
+        }
+    }
+
+    fn propose_admin(&mut self, admin: &ActorId) {
+        self.pending_admin = Some(*admin);
+        reply_ok();
+    }
+
+    /// Makes the proposed admin the admin of the main and logic contracts.
+    async fn accept_admin(&mut self) {
+        let admin = msg::source();
+        assert_eq!(
+            self.pending_admin,
+            Some(admin),
+            "Only the proposed admin can accept the admin role"
+        );
+        match self.send_to_logic(FTLogicAction::SetAdmin(admin)).await {
+            Ok(()) => {
+                self.admin = admin;
+                self.pending_admin = None;
+                reply_ok();
+            }
+            Err(error) => reply_err(error),
+        }
+    }
+
+    /// Sends the action to the logic contract and replies with its result.
+    async fn forward_to_logic(&self, action: FTLogicAction) {
+        match self.send_to_logic(action).await {
+            Ok(()) => reply_ok(),
+            Err(error) => reply_err(error),
+        }
+    }
+
+    async fn send_to_logic(&self, action: FTLogicAction) -> Result<(), FTokenError> {
+        let reply = msg::send_for_reply_as::<_, FTLogicEvent>(self.ft_logic_id, action, 0, 0)
+            .expect("Error in sending a message to the fungible logic contract")
+            .await;
+        match reply {
+            Ok(FTLogicEvent::Ok) => Ok(()),
+            Ok(FTLogicEvent::Err(error)) => Err(error),
+            _ => Err(FTokenError::MessageFailed),
+        }
+    }
+
+    async fn has_role(&self, role: Role, account: &ActorId) -> bool {
+        let reply = msg::send_for_reply_as::<_, FTLogicEvent>(
+            self.ft_logic_id,
+            FTLogicAction::HasRole {
+                role,
+                account: *account,
+            },
+            0,
+            0,
+        )
+        .expect("Error in sending a message `FTLogicAction::HasRole")
+        .await
+        .expect("Unable to decode `FTLogicEvent");
+        matches!(reply, FTLogicEvent::HasRole(true))
+    }
+
+    async fn assert_role(&self, role: Role) {
+        assert!(
+            self.has_role(role, &msg::source()).await,
+            "The account doesn't have the {role:?} role"
+        );
+    }
+
+    fn assert_signer(&self) {
+        assert!(
+            self.signers.contains(&msg::source()),
+            "Only the signers can send that message"
+        );
     }
 
     fn assert_admin(&self) {
@@ -168,11 +648,120 @@ async fn main() {
             FTokenInnerAction::UpdateLogicContract {
                 ft_logic_code_hash,
                 storage_code_hash,
-            } => ftoken.update_logic_contract(ft_logic_code_hash, storage_code_hash),
+            } => {
+                ftoken
+                    .privileged_action(PrivilegedAction::UpdateLogicContract {
+                        ft_logic_code_hash,
+                        storage_code_hash,
+                    })
+                    .await
+            }
             FTokenInnerAction::Clear(transaction_hash) => ftoken.clear(transaction_hash),
             FTokenInnerAction::GetBalance(account) => ftoken.get_balance(&account).await,
             FTokenInnerAction::GetPermitId(account) => ftoken.get_permit_id(&account).await,
-            _ => {}
+            FTokenInnerAction::MigrateStorageAddresses => {
+                ftoken
+                    .privileged_action(PrivilegedAction::MigrateStorageAddresses)
+                    .await
+            }
+            FTokenInnerAction::AddMinter(minter) => {
+                ftoken
+                    .privileged_action(PrivilegedAction::GrantRole {
+                        role: Role::Minter,
+                        account: minter,
+                    })
+                    .await
+            }
+            FTokenInnerAction::RemoveMinter(minter) => {
+                ftoken
+                    .privileged_action(PrivilegedAction::RevokeRole {
+                        role: Role::Minter,
+                        account: minter,
+                    })
+                    .await
+            }
+            FTokenInnerAction::Pause => ftoken.privileged_action(PrivilegedAction::Pause).await,
+            FTokenInnerAction::Unpause => ftoken.privileged_action(PrivilegedAction::Unpause).await,
+            FTokenInnerAction::Freeze(account) => {
+                ftoken
+                    .privileged_action(PrivilegedAction::Freeze(account))
+                    .await
+            }
+            FTokenInnerAction::Unfreeze(account) => {
+                ftoken
+                    .privileged_action(PrivilegedAction::Unfreeze(account))
+                    .await
+            }
+            FTokenInnerAction::ProposeAdmin(admin) => {
+                ftoken
+                    .privileged_action(PrivilegedAction::ProposeAdmin(admin))
+                    .await
+            }
+            FTokenInnerAction::AcceptAdmin => ftoken.accept_admin().await,
+            FTokenInnerAction::AddToAllowlist(account) => {
+                ftoken
+                    .privileged_action(PrivilegedAction::AddToAllowlist(account))
+                    .await
+            }
+            FTokenInnerAction::RemoveFromAllowlist(account) => {
+                ftoken
+                    .privileged_action(PrivilegedAction::RemoveFromAllowlist(account))
+                    .await
+            }
+            FTokenInnerAction::GrantRole { role, account } => {
+                ftoken
+                    .privileged_action(PrivilegedAction::GrantRole { role, account })
+                    .await
+            }
+            FTokenInnerAction::RevokeRole { role, account } => {
+                ftoken
+                    .privileged_action(PrivilegedAction::RevokeRole { role, account })
+                    .await
+            }
+            FTokenInnerAction::RenounceRole(role) => {
+                ftoken
+                    .admin_action(msg::source(), AdminAction::RenounceRole(role))
+                    .await
+            }
+            FTokenInnerAction::HasRole { role, account } => {
+                ftoken.reply_has_role(role, &account).await
+            }
+            FTokenInnerAction::Propose(action) => ftoken.propose(action),
+            FTokenInnerAction::Confirm(proposal_id) => ftoken.confirm(proposal_id).await,
+            FTokenInnerAction::RevokeConfirmation(proposal_id) => {
+                ftoken.revoke_confirmation(proposal_id)
+            }
+            FTokenInnerAction::UpdateStorageCodeHash(storage_code_hash) => {
+                ftoken
+                    .privileged_action(PrivilegedAction::UpdateStorageCodeHash(storage_code_hash))
+                    .await
+            }
+            FTokenInnerAction::ScheduleUpgrade(upgrade) => {
+                ftoken
+                    .privileged_action(PrivilegedAction::ScheduleUpgrade(upgrade))
+                    .await
+            }
+            FTokenInnerAction::CancelUpgrade(upgrade_id) => ftoken.cancel_upgrade(upgrade_id).await,
+            FTokenInnerAction::ExecuteUpgrade(upgrade_id) => {
+                ftoken.execute_upgrade(upgrade_id).await
+            }
+            FTokenInnerAction::GetName => {
+                msg::reply(FTokenEvent::Name(ftoken.name.clone()), 0)
+                    .expect("Error in a reply `FTokenEvent::Name`");
+            }
+            FTokenInnerAction::GetSymbol => {
+                msg::reply(FTokenEvent::Symbol(ftoken.symbol.clone()), 0)
+                    .expect("Error in a reply `FTokenEvent::Symbol`");
+            }
+            FTokenInnerAction::GetDecimals => {
+                msg::reply(FTokenEvent::Decimals(ftoken.decimals), 0)
+                    .expect("Error in a reply `FTokenEvent::Decimals`");
+            }
+            FTokenInnerAction::GetTotalSupply => ftoken.get_total_supply().await,
+            FTokenInnerAction::GetAllowance { owner, spender } => {
+                ftoken.get_allowance(&owner, &spender).await
+            }
+            FTokenInnerAction::Message(_) => {}
         }
     }
 }
@@ -180,19 +769,38 @@ async fn main() {
 #[no_mangle]
 unsafe extern "C" fn init() {
     let init_config: InitFToken = msg::load().expect("Unable to decode `InitFToken`");
+    assert!(
+        init_config.signers.is_empty()
+            || (1..=init_config.signers.len()).contains(&(init_config.threshold as usize)),
+        "The threshold must be in the range from 1 to the number of signers"
+    );
+    assert!(
+        (1..=MAX_SHARD_BITS).contains(&init_config.shard_bits),
+        "The number of shard bits must be in the range from 1 to {MAX_SHARD_BITS}"
+    );
     let (_message_id, ft_logic_id) = ProgramGenerator::create_program(
         init_config.ft_logic_code_hash.into(),
         InitFTLogic {
             admin: msg::source(),
             storage_code_hash: init_config.storage_code_hash,
+            max_supply: init_config.max_supply,
+            shard_bits: init_config.shard_bits,
+            allowlist: init_config.allowlist,
+            multisig: !init_config.signers.is_empty(),
         }
         .encode(),
         0,
     )
     .expect("Error in creating FToken Logic program");
     let ftoken = FToken {
+        name: init_config.name,
+        symbol: init_config.symbol,
+        decimals: init_config.decimals,
         admin: msg::source(),
         ft_logic_id,
+        signers: init_config.signers,
+        threshold: init_config.threshold,
+        upgrade_delay: init_config.upgrade_delay,
         ..Default::default()
     };
 
@@ -203,8 +811,8 @@ fn reply_ok() {
     msg::reply(FTokenEvent::Ok, 0).expect("Error in a reply `FTokenEvent::Ok`");
 }
 
-fn reply_err() {
-    msg::reply(FTokenEvent::Err, 0).expect("Error in a reply `FTokenEvent::Ok`");
+fn reply_err(error: FTokenError) {
+    msg::reply(FTokenEvent::Err(error), 0).expect("Error in a reply `FTokenEvent::Err`");
 }
 
 pub fn get_hash(account: &ActorId, transaction_id: u64) -> H256 {
@@ -227,13 +835,31 @@ fn send_delayed_clear(transaction_hash: H256) {
 extern "C" fn state() {
     let token = unsafe { FTOKEN.as_ref().expect("FToken is not initialized") };
     let token_state = FTokenState {
+        name: token.name.clone(),
+        symbol: token.symbol.clone(),
+        decimals: token.decimals,
         admin: token.admin,
+        pending_admin: token.pending_admin,
         ft_logic_id: token.ft_logic_id,
+        pending_ft_logic_id: token.pending_ft_logic_id,
         transactions: token
             .transactions
             .iter()
             .map(|(key, value)| (*key, *value))
             .collect(),
+        signers: token.signers.clone(),
+        threshold: token.threshold,
+        proposals: token
+            .proposals
+            .iter()
+            .map(|(key, value)| (*key, value.clone()))
+            .collect(),
+        upgrade_delay: token.upgrade_delay,
+        pending_upgrades: token
+            .pending_upgrades
+            .iter()
+            .map(|(key, value)| (*key, value.clone()))
+            .collect(),
     };
     msg::reply(token_state, 0).expect("Failed to share state");
 }
@@ -20,7 +20,7 @@ fn high_load_mint() {
         println!("id is {transaction_id}");
         ftoken.mint(
             transaction_id,
-            transaction_id,
+            HARDCODED_ACCOUNT,
             transaction_id,
             amount,
             false,
@@ -48,7 +48,7 @@ fn high_load_transfer() {
         println!("id is {transaction_id}");
         ftoken.mint(
             transaction_id,
-            transaction_id,
+            HARDCODED_ACCOUNT,
             transaction_id,
             amount,
             false,
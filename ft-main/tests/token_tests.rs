@@ -1,11 +1,45 @@
 pub mod utils;
-use ft_logic_io::PermitUnsigned;
-use gstd::Encode;
+use ft_logic_io::{
+    FTLogicAction, FTLogicEvent, FTLogicState, InitFTLogic, MigrationData, PermitUnsigned,
+    ShardKey, TransactionStatus, TransferAuthorization,
+};
+use ft_main_io::{
+    FTokenAction, FTokenError, FTokenEvent, FTokenState, InitFToken, LogicAction, MultiSignature,
+    PermitNonce, PrivilegedAction, Role, Upgrade,
+};
+use ft_storage_io::{FTStorageAction, FTStorageEvent};
+use gstd::{ActorId, Encode};
 use gtest::{Program, System};
 use hex_literal::hex;
-use sp_core::{sr25519::Pair as Sr25519Pair, Pair};
+use primitive_types::H256;
+use sp_core::{
+    ecdsa::Pair as EcdsaPair, ed25519::Pair as Ed25519Pair, sr25519::Pair as Sr25519Pair, Pair,
+};
 use utils::*;
 
+#[test]
+fn metadata() {
+    let system = System::new();
+    system.init_logger();
+    let ftoken = Program::ftoken(&system);
+
+    let res = ftoken.send(HARDCODED_ACCOUNT, FTokenAction::GetName);
+    assert!(res.contains(&(
+        HARDCODED_ACCOUNT,
+        FTokenEvent::Name(TOKEN_NAME.into()).encode()
+    )));
+    let res = ftoken.send(HARDCODED_ACCOUNT, FTokenAction::GetSymbol);
+    assert!(res.contains(&(
+        HARDCODED_ACCOUNT,
+        FTokenEvent::Symbol(TOKEN_SYMBOL.into()).encode()
+    )));
+    let res = ftoken.send(HARDCODED_ACCOUNT, FTokenAction::GetDecimals);
+    assert!(res.contains(&(
+        HARDCODED_ACCOUNT,
+        FTokenEvent::Decimals(TOKEN_DECIMALS).encode()
+    )));
+}
+
 #[test]
 fn mint() {
     let system = System::new();
@@ -32,6 +66,88 @@ fn mint() {
     ftoken.mint(transaction_id, account, account, amount, false);
     // check balance
     ftoken.check_balance(account, 2 * amount);
+    ftoken.check_total_supply(2 * amount);
+}
+
+#[test]
+fn mint_by_minter() {
+    let system = System::new();
+    system.init_logger();
+    let mut transaction_id: u64 = 0;
+    let minter: u64 = 101;
+    let account: u64 = 200;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken(&system);
+
+    // must fail since the account isn't a minter
+    ftoken.mint(transaction_id, minter, account, amount, true);
+    transaction_id += 1;
+    ftoken.check_balance(account, 0);
+
+    // must fail since only admin can add minters
+    ftoken.send_message_and_check_err(
+        minter,
+        FTokenAction::AddMinter(minter.into()),
+        FTokenError::MissingRole,
+    );
+
+    ftoken.add_minter(HARDCODED_ACCOUNT, minter);
+    ftoken.mint(transaction_id, minter, account, amount, false);
+    transaction_id += 1;
+    ftoken.check_balance(account, amount);
+
+    ftoken.remove_minter(HARDCODED_ACCOUNT, minter);
+    ftoken.mint(transaction_id, minter, account, amount, true);
+    ftoken.check_balance(account, amount);
+}
+
+#[test]
+fn max_supply() {
+    let system = System::new();
+    system.init_logger();
+    let mut transaction_id: u64 = 0;
+    let account: u64 = 200;
+    let max_supply: u128 = 100_000;
+    let ftoken = Program::ftoken_with_config(&system, |config| {
+        config.max_supply = Some(max_supply);
+    });
+
+    ftoken.mint(
+        transaction_id,
+        HARDCODED_ACCOUNT,
+        account,
+        max_supply / 2,
+        false,
+    );
+    transaction_id += 1;
+
+    // must fail since the mint exceeds the maximum supply
+    ftoken.mint(transaction_id, HARDCODED_ACCOUNT, account, max_supply, true);
+    transaction_id += 1;
+    ftoken.check_balance(account, max_supply / 2);
+    ftoken.check_total_supply(max_supply / 2);
+
+    ftoken.mint(
+        transaction_id,
+        HARDCODED_ACCOUNT,
+        account,
+        max_supply / 2,
+        false,
+    );
+    transaction_id += 1;
+    ftoken.check_total_supply(max_supply);
+
+    // burnt tokens can be minted again
+    ftoken.burn(transaction_id, account, account, max_supply / 10, false);
+    transaction_id += 1;
+    ftoken.mint(
+        transaction_id,
+        HARDCODED_ACCOUNT,
+        account,
+        max_supply / 10,
+        false,
+    );
+    ftoken.check_balance(account, max_supply);
 }
 
 #[test]
@@ -62,6 +178,7 @@ fn burn() {
 
     // must fail since account has no enough tokens to burn
     ftoken.burn(transaction_id, account, account, amount, true);
+    ftoken.check_total_supply(amount / 2);
 }
 
 #[test]
@@ -106,6 +223,266 @@ fn transfer() {
     );
 }
 
+#[test]
+fn error_reasons() {
+    let system = System::new();
+    system.init_logger();
+    let account: u64 = 100;
+    let wrong_account: u64 = 101;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken(&system);
+
+    ftoken.mint(0, account, account, amount, false);
+
+    // must fail since the account is not a minter
+    ftoken.send_message_and_check_err(
+        wrong_account,
+        FTokenAction::Message {
+            transaction_id: 0,
+            payload: LogicAction::Mint {
+                recipient: wrong_account.into(),
+                amount,
+            },
+        },
+        FTokenError::NotMinter,
+    );
+
+    // must fail since the account has no enough tokens to burn
+    let burn = || FTokenAction::Message {
+        transaction_id: 1,
+        payload: LogicAction::Burn {
+            sender: account.into(),
+            amount: amount + 1,
+        },
+    };
+    ftoken.send_message_and_check_err(account, burn(), FTokenError::InsufficientBalance);
+    // the replayed transaction reports the same reason
+    ftoken.send_message_and_check_err(account, burn(), FTokenError::InsufficientBalance);
+
+    // must fail since the account is not approved to transfer the tokens
+    ftoken.send_message_and_check_err(
+        wrong_account,
+        FTokenAction::Message {
+            transaction_id: 1,
+            payload: LogicAction::Transfer {
+                sender: account.into(),
+                recipient: wrong_account.into(),
+                amount: amount / 10,
+            },
+        },
+        FTokenError::InsufficientAllowance,
+    );
+    ftoken.check_balance(account, amount);
+}
+
+#[test]
+fn shard_bits() {
+    let sender: u64 = 100;
+    let recipient: u64 = 200;
+    let amount: u128 = 100_000;
+
+    for shard_bits in [1, 8, 12] {
+        let system = System::new();
+        system.init_logger();
+        let mut transaction_id: u64 = 0;
+        let ftoken = Program::ftoken_with_config(&system, |config| {
+            config.shard_bits = shard_bits;
+        });
+
+        ftoken.mint(transaction_id, sender, sender, amount, false);
+        transaction_id += 1;
+        ftoken.transfer(
+            transaction_id,
+            sender,
+            sender,
+            recipient,
+            amount / 10,
+            false,
+        );
+        ftoken.check_balance(sender, amount - amount / 10);
+        ftoken.check_balance(recipient, amount / 10);
+    }
+
+    // must fail since the number of shard bits is out of range
+    let system = System::new();
+    let ftoken = Program::current(&system);
+    let res = ftoken.send(
+        HARDCODED_ACCOUNT,
+        InitFToken {
+            name: TOKEN_NAME.into(),
+            symbol: TOKEN_SYMBOL.into(),
+            decimals: TOKEN_DECIMALS,
+            storage_code_hash: submit_storage_code(&system),
+            ft_logic_code_hash: submit_ft_logic_code(&system),
+            max_supply: None,
+            shard_bits: 13,
+            allowlist: false,
+            signers: vec![],
+            threshold: 0,
+            upgrade_delay: 0,
+        },
+    );
+    assert!(res.main_failed());
+}
+
+#[test]
+fn split_shard() {
+    let system = System::new();
+    system.init_logger();
+    let mut transaction_id: u64 = 0;
+    // both accounts start with the 0 bit, but the second bits differ
+    let first: u64 = 50;
+    let second: u64 = 100;
+    let approved_account: u64 = 300;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken_with_config(&system, |config| {
+        config.shard_bits = 1;
+    });
+
+    ftoken.mint(transaction_id, HARDCODED_ACCOUNT, first, amount, false);
+    transaction_id += 1;
+    ftoken.mint(transaction_id, HARDCODED_ACCOUNT, second, amount, false);
+    transaction_id += 1;
+    ftoken.approve(transaction_id, second, approved_account, amount / 2, false);
+    transaction_id += 1;
+
+    let ft_logic = ftoken.ft_logic(&system);
+    let key = ShardKey { prefix: 0, bits: 1 };
+
+    // must fail since only admin can split shards
+    let res = ft_logic.send(first, FTLogicAction::SplitShard(key));
+    assert!(res.main_failed());
+
+    let res = ft_logic.send(HARDCODED_ACCOUNT, FTLogicAction::SplitShard(key));
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTLogicEvent::Ok.encode())));
+
+    let state: FTLogicState = ft_logic
+        .read_state()
+        .expect("Unable to read `FTLogicState`");
+    assert!(state.split_shards.contains(&key));
+    assert!(state.shard_moves.is_empty());
+    for child in key.children() {
+        assert!(state.id_to_storage.iter().any(|(key, _)| *key == child));
+    }
+    assert!(!state.id_to_storage.iter().any(|(shard, _)| *shard == key));
+
+    // balances and approvals are moved to the new storages
+    ftoken.check_balance(first, amount);
+    ftoken.check_balance(second, amount);
+    ftoken.transfer(
+        transaction_id,
+        approved_account,
+        second,
+        first,
+        amount / 2,
+        false,
+    );
+    transaction_id += 1;
+    ftoken.check_balance(first, amount + amount / 2);
+    ftoken.check_balance(second, amount / 2);
+
+    ftoken.transfer(transaction_id, first, first, second, amount, false);
+    ftoken.check_balance(first, amount / 2);
+    ftoken.check_balance(second, amount + amount / 2);
+    ftoken.check_total_supply(2 * amount);
+}
+
+#[test]
+fn split_shard_in_several_chunks() {
+    let system = System::new();
+    system.init_logger();
+    let amount: u128 = 1_000;
+    // more accounts than are moved in one chunk, all of them start with the 0 bit
+    let accounts: Vec<u64> = (0..150).map(|i| i * 256 + 1).collect();
+    let ftoken = Program::ftoken_with_config(&system, |config| {
+        config.shard_bits = 1;
+    });
+
+    for (transaction_id, account) in accounts.iter().enumerate() {
+        ftoken.mint(
+            transaction_id as u64,
+            HARDCODED_ACCOUNT,
+            *account,
+            amount,
+            false,
+        );
+    }
+
+    let ft_logic = ftoken.ft_logic(&system);
+    let key = ShardKey { prefix: 0, bits: 1 };
+    let res = ft_logic.send(HARDCODED_ACCOUNT, FTLogicAction::SplitShard(key));
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTLogicEvent::Ok.encode())));
+
+    let state: FTLogicState = ft_logic
+        .read_state()
+        .expect("Unable to read `FTLogicState`");
+    assert!(state.split_shards.contains(&key));
+    assert!(state.shard_moves.is_empty());
+
+    for account in &accounts {
+        ftoken.check_balance(*account, amount);
+    }
+    ftoken.check_total_supply(accounts.len() as u128 * amount);
+}
+
+#[test]
+fn upgrade_storage() {
+    let system = System::new();
+    system.init_logger();
+    let mut transaction_id: u64 = 0;
+    let account: u64 = 100;
+    let approved_account: u64 = 300;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken(&system);
+
+    ftoken.mint(transaction_id, HARDCODED_ACCOUNT, account, amount, false);
+    transaction_id += 1;
+    ftoken.approve(transaction_id, account, approved_account, amount / 2, false);
+    transaction_id += 1;
+
+    let ft_logic = ftoken.ft_logic(&system);
+    let key = ShardKey::of(&account.into(), 4);
+    let storage_address = |state: &FTLogicState| {
+        state
+            .id_to_storage
+            .iter()
+            .find(|(shard, _)| *shard == key)
+            .map(|(_, address)| *address)
+            .expect("There is no storage for that shard")
+    };
+    let state: FTLogicState = ft_logic
+        .read_state()
+        .expect("Unable to read `FTLogicState`");
+    let old_storage = storage_address(&state);
+
+    let res = ftoken.send(
+        HARDCODED_ACCOUNT,
+        FTokenAction::UpdateStorageCodeHash(submit_storage_code(&system)),
+    );
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTokenEvent::Ok.encode())));
+    let res = ft_logic.send(HARDCODED_ACCOUNT, FTLogicAction::UpgradeStorage(key));
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTLogicEvent::Ok.encode())));
+
+    let state: FTLogicState = ft_logic
+        .read_state()
+        .expect("Unable to read `FTLogicState`");
+    assert_ne!(storage_address(&state), old_storage);
+    assert!(!state.split_shards.contains(&key));
+
+    // balances and approvals are preserved after the upgrade
+    ftoken.check_balance(account, amount);
+    ftoken.transfer(
+        transaction_id,
+        approved_account,
+        account,
+        approved_account,
+        amount / 2,
+        false,
+    );
+    ftoken.check_balance(account, amount / 2);
+    ftoken.check_balance(approved_account, amount / 2);
+}
+
 #[test]
 fn approve() {
     let system = System::new();
@@ -123,7 +500,9 @@ fn approve() {
     ftoken.check_balance(sender, amount);
     transaction_id += 1;
 
+    ftoken.check_allowance(sender, approved_account, 0);
     ftoken.approve(transaction_id, sender, approved_account, amount / 2, false);
+    ftoken.check_allowance(sender, approved_account, amount / 2);
     transaction_id += 1;
 
     ftoken.transfer(
@@ -139,6 +518,7 @@ fn approve() {
     // check balance
     ftoken.check_balance(sender, amount - amount / 10);
     ftoken.check_balance(recipient, amount / 10);
+    ftoken.check_allowance(sender, approved_account, amount / 2 - amount / 10);
 
     // must fail since approved account tries to transfer more token than allowed amount
     ftoken.transfer(
@@ -151,8 +531,9 @@ fn approve() {
     );
     transaction_id += 1;
 
-    // approve one more time
-    ftoken.approve(transaction_id, sender, approved_account, amount / 10, false);
+    // increase the allowance
+    ftoken.increase_allowance(transaction_id, sender, approved_account, amount / 10, false);
+    ftoken.check_allowance(sender, approved_account, amount / 2);
     transaction_id += 1;
 
     ftoken.transfer(
@@ -175,8 +556,88 @@ fn approve() {
 
     // must fail since sender has no enough tokens
     ftoken.burn(transaction_id, approved_account, sender, amount / 10, false);
+    transaction_id += 1;
 
     ftoken.check_balance(sender, amount - amount / 5 - amount / 2);
+
+    // approve sets the exact allowance
+    ftoken.approve(transaction_id, sender, approved_account, amount / 2, false);
+    transaction_id += 1;
+    ftoken.approve(transaction_id, sender, approved_account, amount / 10, false);
+    ftoken.check_allowance(sender, approved_account, amount / 10);
+    transaction_id += 1;
+
+    // must fail since the allowance is less than the decreased amount
+    ftoken.decrease_allowance(transaction_id, sender, approved_account, amount, true);
+    ftoken.check_allowance(sender, approved_account, amount / 10);
+    transaction_id += 1;
+
+    ftoken.decrease_allowance(transaction_id, sender, approved_account, amount / 20, false);
+    ftoken.check_allowance(sender, approved_account, amount / 20);
+    transaction_id += 1;
+
+    // revoke the approval
+    ftoken.approve(transaction_id, sender, approved_account, 0, false);
+    ftoken.check_allowance(sender, approved_account, 0);
+    transaction_id += 1;
+
+    // must fail since the approval is revoked
+    ftoken.transfer(
+        transaction_id,
+        approved_account,
+        sender,
+        recipient,
+        amount / 20,
+        true,
+    );
+}
+
+#[test]
+fn allowance_replay() {
+    let system = System::new();
+    system.init_logger();
+    let owner: u64 = 100;
+    let approved_account: u64 = 300;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken(&system);
+
+    ftoken.approve(0, owner, approved_account, amount, false);
+
+    let ft_logic = ftoken.ft_logic(&system);
+    let state: FTLogicState = ft_logic
+        .read_state()
+        .expect("Unable to read `FTLogicState`");
+    let (_, storage_id) = state.id_to_storage[0];
+    let storage = system.get_program(<[u8; 32]>::from(storage_id));
+    let ftoken_state: FTokenState = ftoken.read_state().expect("Unable to read `FTokenState`");
+    let ft_logic_id: [u8; 32] = ftoken_state.ft_logic_id.into();
+
+    // the logic contract retries the same message, e.g. after running out of gas
+    let increase = || FTStorageAction::IncreaseAllowance {
+        transaction_hash: [1; 32].into(),
+        msg_source: owner.into(),
+        account: approved_account.into(),
+        amount,
+    };
+    for _ in 0..2 {
+        let res = storage.send(ft_logic_id, increase());
+        assert!(res.contains(&(ft_logic_id, FTStorageEvent::Ok.encode())));
+    }
+    ftoken.check_allowance(owner, approved_account, 2 * amount);
+
+    // a failed change is replayed with the same result
+    let decrease = || FTStorageAction::DecreaseAllowance {
+        transaction_hash: [2; 32].into(),
+        msg_source: owner.into(),
+        account: approved_account.into(),
+        amount: 3 * amount,
+    };
+    storage.send(ft_logic_id, decrease());
+    ftoken.increase_allowance(1, owner, approved_account, amount, false);
+    let res = storage.send(ft_logic_id, decrease());
+    let reply = FTStorageEvent::Err(FTokenError::InsufficientAllowance).encode();
+    assert!(res.contains(&(ft_logic_id, reply)));
+    ftoken.check_allowance(owner, approved_account, 3 * amount);
 }
 
 #[test]
@@ -194,6 +655,8 @@ fn permit() {
         "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60"
     ));
     let owner = pair.public().0;
+    let domain = ftoken.permit_domain();
+    let deadline = u64::MAX;
 
     ftoken.mint(transaction_id, sender, sender, amount, false);
     ftoken.check_balance(sender, amount);
@@ -203,10 +666,12 @@ fn permit() {
     // Check that signing algorithm matches
     {
         let action_permit = PermitUnsigned {
+            domain,
             owner_account: owner.into(),
             approved_account: approved.into(),
             amount,
-            permit_id,
+            permit_id: PermitNonce::Sequential(permit_id),
+            deadline,
         };
         let message_vec = action_permit.encode();
         let message_bytes = message_vec.as_slice();
@@ -230,7 +695,8 @@ fn permit() {
         owner.into(),
         approved.into(),
         amount * 2,
-        permit_id,
+        PermitNonce::Sequential(permit_id),
+        deadline,
         signature.clone(),
         true,
     );
@@ -253,7 +719,8 @@ fn permit() {
         owner.into(),
         approved.into(),
         amount,
-        permit_id,
+        PermitNonce::Sequential(permit_id),
+        deadline,
         signature.clone(),
         false,
     );
@@ -275,7 +742,8 @@ fn permit() {
         owner.into(),
         approved.into(),
         amount,
-        permit_id,
+        PermitNonce::Sequential(permit_id),
+        deadline,
         signature.clone(),
         true,
     );
@@ -289,7 +757,8 @@ fn permit() {
         owner.into(),
         approved.into(),
         amount,
-        permit_id,
+        PermitNonce::Sequential(permit_id),
+        deadline,
         signature,
         true,
     );
@@ -304,10 +773,12 @@ fn permit() {
     let new_signature;
     {
         let action_permit = PermitUnsigned {
+            domain,
             owner_account: owner.into(),
             approved_account: approved.into(),
             amount,
-            permit_id,
+            permit_id: PermitNonce::Sequential(permit_id),
+            deadline,
         };
         let message_vec = action_permit.encode();
         let message_bytes = message_vec.as_slice();
@@ -324,9 +795,1237 @@ fn permit() {
         owner.into(),
         approved.into(),
         amount,
-        permit_id,
+        PermitNonce::Sequential(permit_id),
+        deadline,
         new_signature,
         false,
     );
     ftoken.check_permit_id(owner, 2);
 }
+
+#[test]
+fn permit_deadline_and_domain() {
+    let system = System::new();
+    system.init_logger();
+    let sender: u64 = 100;
+    let approved: u64 = 200;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken(&system);
+    let other_ftoken = Program::ftoken(&system);
+
+    let pair = Sr25519Pair::from_seed(&hex!(
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60"
+    ));
+    let owner = pair.public().0;
+    ftoken.mint(0, sender, sender, amount, false);
+    ftoken.transfer(1, sender, sender, owner, amount, false);
+    system.spend_blocks(1);
+
+    let sign = |domain, deadline| {
+        let permit = PermitUnsigned {
+            domain,
+            owner_account: owner.into(),
+            approved_account: approved.into(),
+            amount,
+            permit_id: PermitNonce::Sequential(0),
+            deadline,
+        };
+        pair.sign(permit.encode().as_slice())
+    };
+    let permit = |transaction_id, deadline| FTokenAction::Message {
+        transaction_id,
+        payload: LogicAction::Permit {
+            owner_account: owner.into(),
+            approved_account: approved.into(),
+            amount,
+            permit_id: PermitNonce::Sequential(0),
+            deadline,
+            sign: MultiSignature::Sr25519(sign(ftoken.permit_domain(), deadline).into()),
+        },
+    };
+
+    // must fail since the deadline has passed
+    ftoken.send_message_and_check_err(sender, permit(2, 0), FTokenError::PermitExpired);
+
+    // must fail since the permit is signed for another token
+    ftoken.permit(
+        3,
+        sender,
+        owner.into(),
+        approved.into(),
+        amount,
+        PermitNonce::Sequential(0),
+        u64::MAX,
+        sign(other_ftoken.permit_domain(), u64::MAX),
+        true,
+    );
+    ftoken.check_permit_id(owner, 0);
+
+    ftoken.send_message_and_check_res(sender, permit(4, u64::MAX), false);
+    ftoken.check_permit_id(owner, 1);
+    ftoken.check_allowance(owner, approved, amount);
+}
+
+#[test]
+fn unordered_permit_nonces() {
+    let system = System::new();
+    system.init_logger();
+    let sender: u64 = 100;
+    let first_spender: u64 = 200;
+    let second_spender: u64 = 300;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken(&system);
+
+    let pair = Sr25519Pair::from_seed(&hex!(
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60"
+    ));
+    let owner = pair.public().0;
+    ftoken.mint(0, sender, sender, amount, false);
+    ftoken.transfer(1, sender, sender, owner, amount, false);
+
+    let permit = |transaction_id, spender: u64, nonce, error| {
+        let permit_id = PermitNonce::Unordered(nonce);
+        let message = PermitUnsigned {
+            domain: ftoken.permit_domain(),
+            owner_account: owner.into(),
+            approved_account: spender.into(),
+            amount,
+            permit_id,
+            deadline: u64::MAX,
+        };
+        ftoken.permit(
+            transaction_id,
+            sender,
+            owner.into(),
+            spender.into(),
+            amount,
+            permit_id,
+            u64::MAX,
+            pair.sign(message.encode().as_slice()),
+            error,
+        );
+    };
+
+    // the permits signed in parallel are used in any order
+    permit(2, second_spender, u128::MAX, false);
+    permit(3, first_spender, 42, false);
+    ftoken.check_allowance(owner, first_spender, amount);
+    ftoken.check_allowance(owner, second_spender, amount);
+    // the sequential permit id isn't affected
+    ftoken.check_permit_id(owner, 0);
+
+    // must fail since the nonce has already been used
+    permit(4, first_spender, 42, true);
+}
+
+#[test]
+fn permit_signature_schemes() {
+    let system = System::new();
+    system.init_logger();
+    let approved: u64 = 200;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken(&system);
+
+    let ed25519_pair = Ed25519Pair::from_seed(&[1; 32]);
+    let ecdsa_pair = EcdsaPair::from_seed(&[2; 32]);
+    let ed25519_owner: ActorId = ed25519_pair.public().0.into();
+    let ecdsa_owner: ActorId = sp_core::blake2_256(ecdsa_pair.public().as_ref()).into();
+
+    let permit_message = |owner| {
+        PermitUnsigned {
+            domain: ftoken.permit_domain(),
+            owner_account: owner,
+            approved_account: approved.into(),
+            amount,
+            permit_id: PermitNonce::Unordered(0),
+            deadline: u64::MAX,
+        }
+        .encode()
+    };
+    let permit = |transaction_id, owner, sign| FTokenAction::Message {
+        transaction_id,
+        payload: LogicAction::Permit {
+            owner_account: owner,
+            approved_account: approved.into(),
+            amount,
+            permit_id: PermitNonce::Unordered(0),
+            deadline: u64::MAX,
+            sign,
+        },
+    };
+
+    // an Ethereum account signs the keccak256 hash of the message with the EIP-191 prefix,
+    // its address is the last 20 bytes of the keccak256 hash of the uncompressed public key
+    let ethereum_pair = EcdsaPair::from_seed(&[3; 32]);
+    let public_key = libsecp256k1::PublicKey::parse_compressed(&ethereum_pair.public().0)
+        .expect("Unable to parse the public key");
+    let mut ethereum_owner = [0; 32];
+    ethereum_owner[12..].copy_from_slice(&sp_core::keccak_256(&public_key.serialize()[1..])[12..]);
+    let ethereum_owner: ActorId = ethereum_owner.into();
+
+    let ed25519_sign = ed25519_pair.sign(&permit_message(ed25519_owner));
+    let ecdsa_sign = ecdsa_pair.sign(&permit_message(ecdsa_owner));
+    let message = permit_message(ethereum_owner);
+    let prefix = format!("\x19Ethereum Signed Message:\n{}", message.len());
+    let mut ethereum_sign = ethereum_pair
+        .sign_prehashed(&sp_core::keccak_256(
+            &[prefix.as_bytes(), &message].concat(),
+        ))
+        .0;
+    // the wallets add 27 to the recovery id
+    ethereum_sign[64] += 27;
+
+    // must fail since the signature is checked with another scheme
+    ftoken.send_message_and_check_err(
+        approved,
+        permit(
+            0,
+            ed25519_owner,
+            MultiSignature::Sr25519(ed25519_sign.0.into()),
+        ),
+        FTokenError::InvalidSignature,
+    );
+    // must fail since the signature is made by another account
+    ftoken.send_message_and_check_err(
+        approved,
+        permit(1, ed25519_owner, MultiSignature::Ecdsa(ecdsa_sign.0)),
+        FTokenError::InvalidSignature,
+    );
+
+    ftoken.send_message_and_check_res(
+        approved,
+        permit(
+            2,
+            ed25519_owner,
+            MultiSignature::Ed25519(ed25519_sign.0.into()),
+        ),
+        false,
+    );
+    ftoken.send_message_and_check_res(
+        approved,
+        permit(3, ecdsa_owner, MultiSignature::Ecdsa(ecdsa_sign.0)),
+        false,
+    );
+    // must fail since the Ethereum signature is checked as a Substrate one
+    ftoken.send_message_and_check_err(
+        approved,
+        permit(4, ethereum_owner, MultiSignature::Ecdsa(ethereum_sign)),
+        FTokenError::InvalidSignature,
+    );
+    ftoken.send_message_and_check_res(
+        approved,
+        permit(
+            5,
+            ethereum_owner,
+            MultiSignature::EthereumEcdsa(ethereum_sign),
+        ),
+        false,
+    );
+    ftoken.check_allowance(ed25519_owner, approved, amount);
+    ftoken.check_allowance(ecdsa_owner, approved, amount);
+    ftoken.check_allowance(ethereum_owner, approved, amount);
+}
+
+#[test]
+fn transfer_with_authorization() {
+    let system = System::new();
+    system.init_logger();
+    let sender: u64 = 100;
+    let recipient: u64 = 200;
+    let relayer: u64 = 300;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken(&system);
+
+    let pair = Sr25519Pair::from_seed(&hex!(
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60"
+    ));
+    let owner = pair.public().0;
+    ftoken.mint(0, sender, sender, amount, false);
+    ftoken.transfer(1, sender, sender, owner, amount, false);
+    system.spend_blocks(1);
+
+    let authorization = TransferAuthorization {
+        domain: ftoken.permit_domain(),
+        sender: owner.into(),
+        recipient: recipient.into(),
+        amount: amount / 2,
+        valid_after: 0,
+        valid_before: u64::MAX,
+        nonce: [7; 32].into(),
+    };
+    let transfer =
+        |transaction_id, authorization: TransferAuthorization, amount| FTokenAction::Message {
+            transaction_id,
+            payload: LogicAction::TransferWithAuthorization {
+                sender: authorization.sender,
+                recipient: authorization.recipient,
+                amount,
+                valid_after: authorization.valid_after,
+                valid_before: authorization.valid_before,
+                nonce: authorization.nonce,
+                sign: MultiSignature::Sr25519(pair.sign(authorization.encode().as_slice()).into()),
+            },
+        };
+
+    // must fail since the signed amount differs
+    ftoken.send_message_and_check_err(
+        relayer,
+        transfer(0, authorization, amount),
+        FTokenError::InvalidSignature,
+    );
+
+    // must fail since the authorization isn't valid yet
+    let not_valid_yet = TransferAuthorization {
+        valid_after: u64::MAX - 1,
+        ..authorization
+    };
+    ftoken.send_message_and_check_err(
+        relayer,
+        transfer(1, not_valid_yet, amount / 2),
+        FTokenError::AuthorizationNotValid,
+    );
+
+    ftoken.send_message_and_check_res(relayer, transfer(2, authorization, amount / 2), false);
+    ftoken.check_balance(owner, amount / 2);
+    ftoken.check_balance(recipient, amount / 2);
+
+    // must fail since the nonce has already been used
+    ftoken.send_message_and_check_err(
+        relayer,
+        transfer(3, authorization, amount / 2),
+        FTokenError::NonceUsed,
+    );
+    ftoken.check_balance(owner, amount / 2);
+}
+
+#[test]
+fn pause() {
+    let system = System::new();
+    system.init_logger();
+    let sender: u64 = 100;
+    let recipient: u64 = 200;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken(&system);
+
+    ftoken.mint(0, sender, sender, amount, false);
+
+    // must fail since only admin can pause the token
+    ftoken.send_message_and_check_err(recipient, FTokenAction::Pause, FTokenError::MissingRole);
+
+    let res = ftoken.send(HARDCODED_ACCOUNT, FTokenAction::Pause);
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTokenEvent::Ok.encode())));
+
+    // must fail since the token is paused
+    ftoken.send_message_and_check_err(
+        sender,
+        FTokenAction::Message {
+            transaction_id: 1,
+            payload: LogicAction::Transfer {
+                sender: sender.into(),
+                recipient: recipient.into(),
+                amount,
+            },
+        },
+        FTokenError::Paused,
+    );
+    ftoken.mint(2, sender, sender, amount, true);
+
+    // the queries keep working
+    ftoken.check_balance(sender, amount);
+    ftoken.check_total_supply(amount);
+
+    let res = ftoken.send(HARDCODED_ACCOUNT, FTokenAction::Unpause);
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTokenEvent::Ok.encode())));
+
+    ftoken.transfer(3, sender, sender, recipient, amount, false);
+    ftoken.check_balance(recipient, amount);
+}
+
+#[test]
+fn freeze() {
+    let system = System::new();
+    system.init_logger();
+    let sender: u64 = 100;
+    let recipient: u64 = 200;
+    let approved_account: u64 = 300;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken(&system);
+
+    ftoken.mint(0, sender, sender, amount, false);
+    ftoken.approve(1, sender, approved_account, amount, false);
+
+    // must fail since only admin can freeze accounts
+    ftoken.send_message_and_check_err(
+        recipient,
+        FTokenAction::Freeze(recipient.into()),
+        FTokenError::MissingRole,
+    );
+
+    let res = ftoken.send(HARDCODED_ACCOUNT, FTokenAction::Freeze(recipient.into()));
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTokenEvent::Ok.encode())));
+
+    // must fail since the frozen account can't receive tokens
+    ftoken.send_message_and_check_err(
+        sender,
+        FTokenAction::Message {
+            transaction_id: 2,
+            payload: LogicAction::Transfer {
+                sender: sender.into(),
+                recipient: recipient.into(),
+                amount,
+            },
+        },
+        FTokenError::AccountFrozen,
+    );
+    ftoken.mint(3, sender, recipient, amount, true);
+    // must fail since the frozen account can't approve
+    ftoken.approve(0, recipient, approved_account, amount, true);
+
+    let res = ftoken.send(HARDCODED_ACCOUNT, FTokenAction::Freeze(sender.into()));
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTokenEvent::Ok.encode())));
+
+    // must fail since the frozen account can't send tokens, even through the approved account
+    ftoken.transfer(0, approved_account, sender, approved_account, amount, true);
+    ftoken.burn(4, sender, sender, amount, true);
+    ftoken.check_balance(sender, amount);
+
+    let res = ftoken.send(HARDCODED_ACCOUNT, FTokenAction::Unfreeze(sender.into()));
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTokenEvent::Ok.encode())));
+    let res = ftoken.send(HARDCODED_ACCOUNT, FTokenAction::Unfreeze(recipient.into()));
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTokenEvent::Ok.encode())));
+
+    ftoken.transfer(5, sender, sender, recipient, amount, false);
+    ftoken.check_balance(recipient, amount);
+}
+
+#[test]
+fn allowlist() {
+    let system = System::new();
+    system.init_logger();
+    let sender: u64 = 100;
+    let recipient: u64 = 200;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken_with_config(&system, |config| {
+        config.allowlist = true;
+    });
+
+    // must fail since the recipient isn't in the allowlist
+    ftoken.send_message_and_check_err(
+        sender,
+        FTokenAction::Message {
+            transaction_id: 0,
+            payload: LogicAction::Mint {
+                recipient: sender.into(),
+                amount,
+            },
+        },
+        FTokenError::NotAllowlisted,
+    );
+
+    // must fail since only admin can manage the allowlist
+    ftoken.send_message_and_check_err(
+        sender,
+        FTokenAction::AddToAllowlist(sender.into()),
+        FTokenError::MissingRole,
+    );
+
+    let res = ftoken.send(
+        HARDCODED_ACCOUNT,
+        FTokenAction::AddToAllowlist(sender.into()),
+    );
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTokenEvent::Ok.encode())));
+    ftoken.mint(1, sender, sender, amount, false);
+
+    // must fail since the recipient isn't in the allowlist
+    ftoken.transfer(2, sender, sender, recipient, amount, true);
+
+    let res = ftoken.send(
+        HARDCODED_ACCOUNT,
+        FTokenAction::AddToAllowlist(recipient.into()),
+    );
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTokenEvent::Ok.encode())));
+    ftoken.transfer(3, sender, sender, recipient, amount / 2, false);
+
+    let res = ftoken.send(
+        HARDCODED_ACCOUNT,
+        FTokenAction::RemoveFromAllowlist(sender.into()),
+    );
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTokenEvent::Ok.encode())));
+    // must fail since the sender is removed from the allowlist
+    ftoken.transfer(4, sender, sender, recipient, amount / 2, true);
+    ftoken.check_balance(sender, amount / 2);
+    ftoken.check_balance(recipient, amount / 2);
+}
+
+#[test]
+fn admin_transfer() {
+    let system = System::new();
+    system.init_logger();
+    let new_admin: u64 = 200;
+    let account: u64 = 300;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken(&system);
+
+    // must fail since only admin can propose the new admin
+    let res = ftoken.send(new_admin, FTokenAction::ProposeAdmin(new_admin.into()));
+    assert!(res.main_failed());
+
+    let res = ftoken.send(
+        HARDCODED_ACCOUNT,
+        FTokenAction::ProposeAdmin(new_admin.into()),
+    );
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTokenEvent::Ok.encode())));
+
+    // must fail since only the proposed admin can accept the role
+    let res = ftoken.send(account, FTokenAction::AcceptAdmin);
+    assert!(res.main_failed());
+
+    let res = ftoken.send(new_admin, FTokenAction::AcceptAdmin);
+    assert!(res.contains(&(new_admin, FTokenEvent::Ok.encode())));
+
+    let state: FTokenState = ftoken.read_state().expect("Unable to read `FTokenState`");
+    assert_eq!(state.admin, new_admin.into());
+    assert_eq!(state.pending_admin, None);
+    let state: FTLogicState = ftoken
+        .ft_logic(&system)
+        .read_state()
+        .expect("Unable to read `FTLogicState`");
+    assert_eq!(state.admin, new_admin.into());
+
+    // must fail since the previous admin has no rights anymore
+    ftoken.send_message_and_check_err(
+        HARDCODED_ACCOUNT,
+        FTokenAction::AddMinter(account.into()),
+        FTokenError::MissingRole,
+    );
+    ftoken.mint(0, HARDCODED_ACCOUNT, account, amount, true);
+
+    ftoken.mint(0, new_admin, account, amount, false);
+    ftoken.add_minter(new_admin, account);
+    ftoken.check_balance(account, amount);
+}
+
+#[test]
+fn roles() {
+    let system = System::new();
+    system.init_logger();
+    let operator: u64 = 200;
+    let account: u64 = 300;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken(&system);
+    let has_role = |role: Role, account: u64, expected: bool| {
+        let res = ftoken.send(
+            account,
+            FTokenAction::HasRole {
+                role,
+                account: account.into(),
+            },
+        );
+        assert!(res.contains(&(account, FTokenEvent::HasRole(expected).encode())));
+    };
+
+    // the admin has all the roles except the burner one
+    has_role(Role::Upgrader, HARDCODED_ACCOUNT, true);
+    has_role(Role::Burner, HARDCODED_ACCOUNT, false);
+    has_role(Role::Pauser, operator, false);
+
+    // must fail since only admin can grant the roles
+    ftoken.send_message_and_check_err(
+        operator,
+        FTokenAction::GrantRole {
+            role: Role::Pauser,
+            account: operator.into(),
+        },
+        FTokenError::MissingRole,
+    );
+
+    for role in [Role::Pauser, Role::Burner] {
+        let res = ftoken.send(
+            HARDCODED_ACCOUNT,
+            FTokenAction::GrantRole {
+                role,
+                account: operator.into(),
+            },
+        );
+        assert!(res.contains(&(HARDCODED_ACCOUNT, FTokenEvent::Ok.encode())));
+    }
+    has_role(Role::Pauser, operator, true);
+
+    // the pauser can pause the token but can't freeze the accounts
+    let res = ftoken.send(operator, FTokenAction::Pause);
+    assert!(res.contains(&(operator, FTokenEvent::Ok.encode())));
+    let res = ftoken.send(operator, FTokenAction::Unpause);
+    assert!(res.contains(&(operator, FTokenEvent::Ok.encode())));
+    ftoken.send_message_and_check_err(
+        operator,
+        FTokenAction::Freeze(account.into()),
+        FTokenError::MissingRole,
+    );
+
+    // the burner burns the tokens without an allowance
+    ftoken.mint(0, HARDCODED_ACCOUNT, account, amount, false);
+    // must fail since the admin isn't a burner
+    ftoken.burn(4, HARDCODED_ACCOUNT, account, amount, true);
+    ftoken.burn(1, operator, account, amount, false);
+    ftoken.check_balance(account, 0);
+
+    let res = ftoken.send(operator, FTokenAction::RenounceRole(Role::Burner));
+    assert!(res.contains(&(operator, FTokenEvent::Ok.encode())));
+    has_role(Role::Burner, operator, false);
+    ftoken.mint(2, HARDCODED_ACCOUNT, account, amount, false);
+    ftoken.burn(3, operator, account, amount, true);
+
+    let res = ftoken.send(
+        HARDCODED_ACCOUNT,
+        FTokenAction::RevokeRole {
+            role: Role::Pauser,
+            account: operator.into(),
+        },
+    );
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTokenEvent::Ok.encode())));
+    ftoken.send_message_and_check_err(operator, FTokenAction::Pause, FTokenError::MissingRole);
+}
+
+#[test]
+fn multisig() {
+    let system = System::new();
+    system.init_logger();
+    let signers: [u64; 3] = [200, 300, 400];
+    let minter: u64 = 500;
+    let pauser: u64 = 600;
+    let ftoken = Program::ftoken_with_config(&system, |config| {
+        config.signers = signers.iter().map(|signer| (*signer).into()).collect();
+        config.threshold = 2;
+    });
+    let propose = |action: PrivilegedAction| -> H256 {
+        let res = ftoken.send(signers[0], FTokenAction::Propose(action.clone()));
+        let state: FTokenState = ftoken.read_state().expect("Unable to read `FTokenState`");
+        let (proposal_id, _) = state
+            .proposals
+            .into_iter()
+            .find(|(_, proposal)| proposal.action == action)
+            .expect("The proposal doesn't exist");
+        assert!(res.contains(&(signers[0], FTokenEvent::Proposed(proposal_id).encode())));
+        proposal_id
+    };
+
+    // must fail since the privileged actions must be proposed to the signers
+    let res = ftoken.send(
+        HARDCODED_ACCOUNT,
+        FTokenAction::UpdateLogicContract {
+            ft_logic_code_hash: submit_ft_logic_code(&system),
+            storage_code_hash: submit_storage_code(&system),
+        },
+    );
+    assert!(res.main_failed());
+    let res = ftoken.send(HARDCODED_ACCOUNT, FTokenAction::AddMinter(minter.into()));
+    assert!(res.main_failed());
+
+    // must fail since only the signers can propose the actions
+    let res = ftoken.send(
+        HARDCODED_ACCOUNT,
+        FTokenAction::Propose(PrivilegedAction::MigrateStorageAddresses),
+    );
+    assert!(res.main_failed());
+
+    // the admin has no implicit roles, so it can't bypass the signers
+    ftoken.send_message_and_check_err(
+        HARDCODED_ACCOUNT,
+        FTokenAction::Pause,
+        FTokenError::MissingRole,
+    );
+    let res = ftoken.send(
+        HARDCODED_ACCOUNT,
+        FTokenAction::HasRole {
+            role: Role::Upgrader,
+            account: HARDCODED_ACCOUNT.into(),
+        },
+    );
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTokenEvent::HasRole(false).encode())));
+    let res = ftoken.ft_logic(&system).send(
+        HARDCODED_ACCOUNT,
+        FTLogicAction::SplitShard(ShardKey { prefix: 0, bits: 4 }),
+    );
+    assert!(res.main_failed());
+    ftoken.mint(0, HARDCODED_ACCOUNT, HARDCODED_ACCOUNT, 1_000, true);
+
+    let proposal_id = propose(PrivilegedAction::GrantRole {
+        role: Role::Minter,
+        account: minter.into(),
+    });
+    let res = ftoken.send(signers[0], FTokenAction::Confirm(proposal_id));
+    assert!(res.contains(&(signers[0], FTokenEvent::Ok.encode())));
+    // must fail since the signer has already confirmed the proposal
+    let res = ftoken.send(signers[0], FTokenAction::Confirm(proposal_id));
+    assert!(res.main_failed());
+    // the threshold isn't reached yet
+    ftoken.mint(0, minter, minter, 1_000, true);
+
+    let res = ftoken.send(signers[1], FTokenAction::Confirm(proposal_id));
+    assert!(res.contains(&(signers[1], FTokenEvent::Ok.encode())));
+    ftoken.mint(1, minter, minter, 1_000, false);
+    let state: FTokenState = ftoken.read_state().expect("Unable to read `FTokenState`");
+    assert!(state.proposals.is_empty());
+
+    // the withdrawn confirmation doesn't count
+    let proposal_id = propose(PrivilegedAction::UpdateLogicContract {
+        ft_logic_code_hash: submit_ft_logic_code(&system),
+        storage_code_hash: submit_storage_code(&system),
+    });
+    let res = ftoken.send(signers[1], FTokenAction::Confirm(proposal_id));
+    assert!(res.contains(&(signers[1], FTokenEvent::Ok.encode())));
+    let res = ftoken.send(signers[1], FTokenAction::RevokeConfirmation(proposal_id));
+    assert!(res.contains(&(signers[1], FTokenEvent::Ok.encode())));
+    let res = ftoken.send(signers[2], FTokenAction::Confirm(proposal_id));
+    assert!(res.contains(&(signers[2], FTokenEvent::Ok.encode())));
+    let state: FTokenState = ftoken.read_state().expect("Unable to read `FTokenState`");
+    assert_eq!(state.pending_ft_logic_id, None);
+
+    let res = ftoken.send(signers[0], FTokenAction::Confirm(proposal_id));
+    assert!(res.contains(&(signers[0], FTokenEvent::Ok.encode())));
+    let state: FTokenState = ftoken.read_state().expect("Unable to read `FTokenState`");
+    assert!(state.pending_ft_logic_id.is_some());
+
+    // the actions checked by the logic contract are confirmed by the signers as well
+    let proposal_id = propose(PrivilegedAction::Pause);
+    for signer in &signers[..2] {
+        let res = ftoken.send(*signer, FTokenAction::Confirm(proposal_id));
+        assert!(res.contains(&(*signer, FTokenEvent::Ok.encode())));
+    }
+    ftoken.send_message_and_check_err(
+        minter,
+        FTokenAction::Message {
+            transaction_id: 2,
+            payload: LogicAction::Mint {
+                recipient: minter.into(),
+                amount: 1_000,
+            },
+        },
+        FTokenError::Paused,
+    );
+
+    // the account granted the role by the signers sends the action directly
+    let proposal_id = propose(PrivilegedAction::GrantRole {
+        role: Role::Pauser,
+        account: pauser.into(),
+    });
+    for signer in &signers[..2] {
+        let res = ftoken.send(*signer, FTokenAction::Confirm(proposal_id));
+        assert!(res.contains(&(*signer, FTokenEvent::Ok.encode())));
+    }
+    let res = ftoken.send(pauser, FTokenAction::Unpause);
+    assert!(res.contains(&(pauser, FTokenEvent::Ok.encode())));
+    ftoken.mint(3, minter, minter, 1_000, false);
+}
+
+#[test]
+fn timelocked_upgrade() {
+    let system = System::new();
+    system.init_logger();
+    let account: u64 = 200;
+    let upgrade_delay: u32 = 10;
+    let ftoken = Program::ftoken_with_config(&system, |config| {
+        config.upgrade_delay = upgrade_delay;
+    });
+    let upgrade = Upgrade::UpdateLogicContract {
+        ft_logic_code_hash: submit_ft_logic_code(&system),
+        storage_code_hash: submit_storage_code(&system),
+    };
+    let schedule = || -> H256 {
+        let res = ftoken.send(
+            HARDCODED_ACCOUNT,
+            FTokenAction::ScheduleUpgrade(upgrade.clone()),
+        );
+        let state: FTokenState = ftoken.read_state().expect("Unable to read `FTokenState`");
+        let (upgrade_id, _) = *state
+            .pending_upgrades
+            .last()
+            .expect("The upgrade isn't scheduled");
+        assert!(res.contains(&(
+            HARDCODED_ACCOUNT,
+            FTokenEvent::UpgradeScheduled(upgrade_id).encode()
+        )));
+        upgrade_id
+    };
+
+    // must fail since the upgrades must be scheduled
+    let res = ftoken.send(
+        HARDCODED_ACCOUNT,
+        FTokenAction::UpdateLogicContract {
+            ft_logic_code_hash: submit_ft_logic_code(&system),
+            storage_code_hash: submit_storage_code(&system),
+        },
+    );
+    assert!(res.main_failed());
+    let res = ftoken.send(
+        HARDCODED_ACCOUNT,
+        FTokenAction::UpdateStorageCodeHash(submit_storage_code(&system)),
+    );
+    assert!(res.main_failed());
+
+    // must fail since only the upgrader can schedule the upgrades
+    let res = ftoken.send(account, FTokenAction::ScheduleUpgrade(upgrade.clone()));
+    assert!(res.main_failed());
+
+    let upgrade_id = schedule();
+    let state: FTokenState = ftoken.read_state().expect("Unable to read `FTokenState`");
+    assert_eq!(state.pending_upgrades.len(), 1);
+    assert_eq!(state.pending_upgrades[0].1.upgrade, upgrade);
+
+    // must fail since only the upgraders can execute the upgrades
+    let res = ftoken.send(account, FTokenAction::ExecuteUpgrade(upgrade_id));
+    assert!(res.main_failed());
+    // must fail since the upgrade delay hasn't passed yet
+    let res = ftoken.send(HARDCODED_ACCOUNT, FTokenAction::ExecuteUpgrade(upgrade_id));
+    assert!(res.main_failed());
+    // must fail since only the upgrader can cancel the upgrades
+    let res = ftoken.send(account, FTokenAction::CancelUpgrade(upgrade_id));
+    assert!(res.main_failed());
+
+    let res = ftoken.send(HARDCODED_ACCOUNT, FTokenAction::CancelUpgrade(upgrade_id));
+    assert!(res.contains(&(HARDCODED_ACCOUNT, FTokenEvent::Ok.encode())));
+    system.spend_blocks(upgrade_delay + 1);
+    let state: FTokenState = ftoken.read_state().expect("Unable to read `FTokenState`");
+    assert!(state.pending_upgrades.is_empty());
+    assert_eq!(state.pending_ft_logic_id, None);
+
+    schedule();
+    system.spend_blocks(upgrade_delay - 1);
+    let state: FTokenState = ftoken.read_state().expect("Unable to read `FTokenState`");
+    assert_eq!(state.pending_ft_logic_id, None);
+
+    system.spend_blocks(2);
+    let state: FTokenState = ftoken.read_state().expect("Unable to read `FTokenState`");
+    assert!(state.pending_upgrades.is_empty());
+    assert!(state.pending_ft_logic_id.is_some());
+}
+
+#[test]
+fn batch_transfer() {
+    let system = System::new();
+    system.init_logger();
+    let sender: u64 = 100;
+    let approved_account: u64 = 400;
+    // 101 is in the sender's storage, 200 and 201 share a storage
+    let recipients: [u64; 4] = [101, 200, 201, 300];
+    let amount: u128 = 10_000;
+    let ftoken = Program::ftoken(&system);
+    let batch_transfer = |transaction_id: u64, amount: u128| FTokenAction::Message {
+        transaction_id,
+        payload: LogicAction::BatchTransfer {
+            sender: sender.into(),
+            transfers: recipients
+                .iter()
+                .map(|recipient| ((*recipient).into(), amount))
+                .collect(),
+        },
+    };
+
+    ftoken.mint(0, HARDCODED_ACCOUNT, sender, 4 * amount, false);
+    ftoken.send_message_and_check_res(sender, batch_transfer(1, amount), false);
+    ftoken.check_balance(sender, 0);
+    for recipient in recipients {
+        ftoken.check_balance(recipient, amount);
+    }
+
+    // must fail since the sender has no tokens left, none of the recipients receive the tokens
+    ftoken.send_message_and_check_err(
+        sender,
+        batch_transfer(2, amount),
+        FTokenError::InsufficientBalance,
+    );
+    for recipient in recipients {
+        ftoken.check_balance(recipient, amount);
+    }
+
+    // the approved account transfers the tokens on behalf of the sender
+    ftoken.mint(3, HARDCODED_ACCOUNT, sender, 4 * amount, false);
+    ftoken.approve(4, sender, approved_account, 2 * amount, false);
+    ftoken.send_message_and_check_err(
+        approved_account,
+        batch_transfer(5, amount),
+        FTokenError::InsufficientAllowance,
+    );
+    ftoken.send_message_and_check_res(approved_account, batch_transfer(6, amount / 2), false);
+    ftoken.check_balance(sender, 2 * amount);
+    for recipient in recipients {
+        ftoken.check_balance(recipient, amount + amount / 2);
+    }
+    ftoken.check_allowance(sender, approved_account, 0);
+    ftoken.check_total_supply(8 * amount);
+
+    // must fail since the total amount overflows
+    ftoken.send_message_and_check_err(
+        sender,
+        batch_transfer(7, u128::MAX / 2),
+        FTokenError::AmountOverflow,
+    );
+}
+
+#[test]
+fn failed_compensation() {
+    let system = System::new();
+    system.init_logger();
+    // the account acts as the main contract of the logic contract
+    let main: u64 = 1;
+    let sender: u64 = 100;
+    let recipient: u64 = 200;
+    let amount: u128 = 100_000;
+    let transfer_hash = H256::from_low_u64_be(1);
+    let batch_mint_hash = H256::from_low_u64_be(2);
+    let actor_id = |program: &Program| -> ActorId {
+        let id: [u8; 32] = program
+            .id()
+            .as_ref()
+            .try_into()
+            .expect("Unable to get the program id");
+        id.into()
+    };
+    let compensation_hash = |transaction_hash: H256| -> H256 {
+        sp_core_hashing::blake2_256(&(transaction_hash, "compensation").encode()).into()
+    };
+
+    let ft_logic = Program::from_file(
+        &system,
+        "../target/wasm32-unknown-unknown/debug/ft_logic.opt.wasm",
+    );
+    let res = ft_logic.send(
+        main,
+        InitFTLogic {
+            admin: main.into(),
+            storage_code_hash: submit_storage_code(&system),
+            max_supply: None,
+            shard_bits: 1,
+            allowlist: false,
+            multisig: false,
+        },
+    );
+    assert!(!res.main_failed());
+
+    let storage = || {
+        let storage = Program::from_file(
+            &system,
+            "../target/wasm32-unknown-unknown/debug/ft_storage.opt.wasm",
+        );
+        let res = storage.send_bytes(main, b"");
+        assert!(!res.main_failed());
+        storage
+    };
+    // the batch mint credits the storages in the order of their addresses,
+    // so the sender storage is credited first
+    let (first, second) = (storage(), storage());
+    let (sender_storage, recipient_storage) = if actor_id(&first) < actor_id(&second) {
+        (first, second)
+    } else {
+        (second, first)
+    };
+
+    let res = sender_storage.send(
+        main,
+        FTStorageAction::IncreaseBalance {
+            transaction_hash: H256::from_low_u64_be(3),
+            account: sender.into(),
+            amount,
+        },
+    );
+    assert!(res.contains(&(main, FTStorageEvent::Ok.encode())));
+    // the results of the compensations are taken by other messages
+    let res = sender_storage.send(
+        main,
+        FTStorageAction::DecreaseBalance {
+            transaction_hash: compensation_hash(transfer_hash),
+            msg_source: sender.into(),
+            account: sender.into(),
+            amount: u128::MAX,
+        },
+    );
+    assert!(res.contains(&(
+        main,
+        FTStorageEvent::Err(FTokenError::InsufficientBalance).encode()
+    )));
+    let credit_hash: H256 = sp_core_hashing::blake2_256(&(batch_mint_hash, 0u32).encode()).into();
+    let res = sender_storage.send(
+        main,
+        FTStorageAction::DecreaseBalances {
+            transaction_hash: compensation_hash(credit_hash),
+            accounts: vec![(sender.into(), u128::MAX)],
+        },
+    );
+    assert!(res.contains(&(
+        main,
+        FTStorageEvent::Err(FTokenError::InsufficientBalance).encode()
+    )));
+    // the recipient storage isn't handed over, so the credits fail
+    let res = sender_storage.send(
+        main,
+        FTStorageAction::UpdateLogicContract(actor_id(&ft_logic)),
+    );
+    assert!(res.contains(&(main, FTStorageEvent::Ok.encode())));
+
+    let res = ft_logic.send(
+        main,
+        FTLogicAction::ImportStorages(MigrationData {
+            admin: main.into(),
+            shard_bits: 1,
+            id_to_storage: vec![
+                (ShardKey { prefix: 0, bits: 1 }, actor_id(&sender_storage)),
+                (
+                    ShardKey { prefix: 1, bits: 1 },
+                    actor_id(&recipient_storage),
+                ),
+            ],
+            split_shards: vec![],
+            instructions: vec![],
+            transaction_status: vec![],
+            roles: Default::default(),
+            total_supply: amount,
+            max_supply: None,
+            paused: false,
+            frozen: vec![],
+            allowlist_enabled: false,
+            allowlist: vec![],
+        }),
+    );
+    assert!(res.contains(&(main, FTLogicEvent::Ok.encode())));
+
+    let transfer = FTLogicAction::Message {
+        transaction_hash: transfer_hash,
+        account: sender.into(),
+        payload: LogicAction::Transfer {
+            sender: sender.into(),
+            recipient: recipient.into(),
+            amount,
+        }
+        .encode(),
+    };
+    let batch_mint = FTLogicAction::Message {
+        transaction_hash: batch_mint_hash,
+        account: main.into(),
+        payload: LogicAction::BatchMint(vec![(sender.into(), amount), (recipient.into(), amount)])
+            .encode(),
+    };
+    // the resent transactions resume the compensations instead of replying with saved failures
+    for _ in 0..2 {
+        for (transaction_hash, action) in
+            [(transfer_hash, &transfer), (batch_mint_hash, &batch_mint)]
+        {
+            let res = ft_logic.send(main, action.clone());
+            assert!(res.contains(&(
+                main,
+                FTLogicEvent::Err(FTokenError::CompensationFailed).encode()
+            )));
+            let state: FTLogicState = ft_logic
+                .read_state()
+                .expect("Unable to read `FTLogicState`");
+            assert!(state
+                .transaction_status
+                .iter()
+                .any(|(hash, status)| *hash == transaction_hash
+                    && matches!(status, TransactionStatus::InProgress)));
+        }
+        // the credit of the batch mint that isn't compensated is counted once
+        let state: FTLogicState = ft_logic
+            .read_state()
+            .expect("Unable to read `FTLogicState`");
+        assert_eq!(state.total_supply, 2 * amount);
+    }
+}
+
+#[test]
+fn batch_mint() {
+    let system = System::new();
+    system.init_logger();
+    let recipients: [u64; 4] = [100, 101, 200, 300];
+    let amount: u128 = 10_000;
+    let ftoken = Program::ftoken_with_config(&system, |config| {
+        config.max_supply = Some(5 * amount);
+    });
+    let batch_mint = |transaction_id: u64, amount: u128| FTokenAction::Message {
+        transaction_id,
+        payload: LogicAction::BatchMint(
+            recipients
+                .iter()
+                .map(|recipient| ((*recipient).into(), amount))
+                .collect(),
+        ),
+    };
+
+    // must fail since only the minters can mint the tokens
+    ftoken.send_message_and_check_err(recipients[0], batch_mint(0, amount), FTokenError::NotMinter);
+    // must fail since the total amount exceeds the maximum supply
+    ftoken.send_message_and_check_err(
+        HARDCODED_ACCOUNT,
+        batch_mint(1, 2 * amount),
+        FTokenError::MaxSupplyExceeded,
+    );
+    // must fail since the total amount overflows
+    ftoken.send_message_and_check_err(
+        HARDCODED_ACCOUNT,
+        batch_mint(3, u128::MAX / 2),
+        FTokenError::AmountOverflow,
+    );
+    ftoken.check_total_supply(0);
+
+    ftoken.send_message_and_check_res(HARDCODED_ACCOUNT, batch_mint(2, amount), false);
+    // the retried transaction is applied once
+    ftoken.send_message_and_check_res(HARDCODED_ACCOUNT, batch_mint(2, amount), false);
+    for recipient in recipients {
+        ftoken.check_balance(recipient, amount);
+    }
+    ftoken.check_total_supply(4 * amount);
+}
+
+#[test]
+fn failed_migration() {
+    let system = System::new();
+    system.init_logger();
+    let sender: u64 = 100;
+    let recipient: u64 = 200;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken(&system);
+    ftoken.mint(0, sender, sender, amount, false);
+
+    // the new logic contract fails to initialize, so it can't import the storages
+    let broken_code_hash: [u8; 32] = system
+        .submit_code("../target/wasm32-unknown-unknown/debug/ft_main.opt.wasm")
+        .into();
+    ftoken.update_logic_contract(
+        HARDCODED_ACCOUNT,
+        broken_code_hash.into(),
+        submit_storage_code(&system),
+    );
+    let res = ftoken.send(HARDCODED_ACCOUNT, FTokenAction::MigrateStorageAddresses);
+    assert!(res.contains(&(
+        HARDCODED_ACCOUNT,
+        FTokenEvent::Err(FTokenError::MessageFailed).encode()
+    )));
+
+    // the current logic contract keeps the storages and serves the messages
+    ftoken.transfer(1, sender, sender, recipient, amount / 2, false);
+    ftoken.check_balance(recipient, amount / 2);
+
+    ftoken.update_logic_contract(
+        HARDCODED_ACCOUNT,
+        submit_ft_logic_code(&system),
+        submit_storage_code(&system),
+    );
+    ftoken.migrate_storage_addresses(HARDCODED_ACCOUNT);
+    ftoken.check_balance(sender, amount / 2);
+    ftoken.check_balance(recipient, amount / 2);
+    ftoken.transfer(2, recipient, recipient, sender, amount / 2, false);
+    ftoken.check_balance(sender, amount);
+}
+
+#[test]
+fn failed_storage_hand_over() {
+    let system = System::new();
+    system.init_logger();
+    let sender: u64 = 100;
+    let recipient: u64 = 200;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken(&system);
+    ftoken.mint(0, sender, sender, amount, false);
+
+    // the storage created from the broken code can't accept the new logic contract
+    let broken_code_hash: [u8; 32] = system
+        .submit_code("../target/wasm32-unknown-unknown/debug/ft_main.opt.wasm")
+        .into();
+    ftoken.send_message_and_check_res(
+        HARDCODED_ACCOUNT,
+        FTokenAction::UpdateStorageCodeHash(broken_code_hash.into()),
+        false,
+    );
+    ftoken.mint(1, sender, recipient, amount, true);
+    ftoken.send_message_and_check_res(
+        HARDCODED_ACCOUNT,
+        FTokenAction::UpdateStorageCodeHash(submit_storage_code(&system)),
+        false,
+    );
+
+    ftoken.update_logic_contract(
+        HARDCODED_ACCOUNT,
+        submit_ft_logic_code(&system),
+        submit_storage_code(&system),
+    );
+    ftoken.send_message_and_check_err(
+        HARDCODED_ACCOUNT,
+        FTokenAction::MigrateStorageAddresses,
+        FTokenError::MessageFailed,
+    );
+
+    // the current logic contract isn't marked as migrated and keeps serving the messages
+    let account: u64 = 17;
+    ftoken.mint(2, sender, account, amount, false);
+    ftoken.check_balance(account, amount);
+    ftoken.check_total_supply(2 * amount);
+}
+
+#[test]
+fn update_logic_contract() {
+    let system = System::new();
+    system.init_logger();
+    let mut transaction_id: u64 = 0;
+    let sender: u64 = 100;
+    let recipient: u64 = 200;
+    let amount: u128 = 100_000;
+    let ftoken = Program::ftoken(&system);
+
+    ftoken.mint(transaction_id, sender, sender, amount, false);
+    transaction_id += 1;
+    ftoken.transfer(
+        transaction_id,
+        sender,
+        sender,
+        recipient,
+        amount / 10,
+        false,
+    );
+    transaction_id += 1;
+
+    // must fail since only admin can update the logic contract
+    let res = ftoken.send(
+        recipient,
+        FTokenAction::UpdateLogicContract {
+            ft_logic_code_hash: submit_ft_logic_code(&system),
+            storage_code_hash: submit_storage_code(&system),
+        },
+    );
+    assert!(res.main_failed());
+
+    ftoken.update_logic_contract(
+        HARDCODED_ACCOUNT,
+        submit_ft_logic_code(&system),
+        submit_storage_code(&system),
+    );
+    // the old logic contract keeps serving until the storages are migrated
+    ftoken.check_balance(sender, amount - amount / 10);
+
+    ftoken.migrate_storage_addresses(HARDCODED_ACCOUNT);
+
+    // balances are preserved after the migration
+    ftoken.check_balance(sender, amount - amount / 10);
+    ftoken.check_balance(recipient, amount / 10);
+    ftoken.check_total_supply(amount);
+
+    // the transaction that has already been processed isn't executed again
+    ftoken.transfer(
+        transaction_id - 1,
+        sender,
+        sender,
+        recipient,
+        amount / 10,
+        false,
+    );
+    ftoken.check_balance(recipient, amount / 10);
+
+    ftoken.transfer(
+        transaction_id,
+        recipient,
+        recipient,
+        sender,
+        amount / 10,
+        false,
+    );
+    ftoken.check_balance(sender, amount);
+    ftoken.check_balance(recipient, 0);
+}
@@ -1,13 +1,23 @@
+use ft_logic_io::{PermitDomain, PERMIT_VERSION};
 use ft_main_io::*;
 use gstd::{prelude::*, ActorId};
 use gtest::{Log, Program, System};
+use primitive_types::H256;
 use sp_core::sr25519::Signature;
 
 pub trait FToken {
     fn ftoken(system: &System) -> Program;
+    fn ftoken_with_config(system: &System, config: impl FnOnce(&mut InitFToken)) -> Program;
     fn mint(&self, transaction_id: u64, from: u64, account: u64, amount: u128, error: bool);
     fn check_balance(&self, account: impl Into<ActorId>, expected_amount: u128);
     fn check_permit_id(&self, account: [u8; 32], expected_permit_id: u128);
+    fn check_total_supply(&self, expected_total_supply: u128);
+    fn check_allowance(
+        &self,
+        owner: impl Into<ActorId>,
+        spender: impl Into<ActorId>,
+        expected_allowance: u128,
+    );
     fn burn(&self, transaction_id: u64, from: u64, account: u64, amount: u128, error: bool);
     fn transfer(
         &self,
@@ -26,6 +36,22 @@ pub trait FToken {
         amount: u128,
         error: bool,
     );
+    fn increase_allowance(
+        &self,
+        transaction_id: u64,
+        from: u64,
+        approved_account: u64,
+        amount: u128,
+        error: bool,
+    );
+    fn decrease_allowance(
+        &self,
+        transaction_id: u64,
+        from: u64,
+        approved_account: u64,
+        amount: u128,
+        error: bool,
+    );
 
     #[allow(clippy::too_many_arguments)]
     fn permit(
@@ -35,33 +61,66 @@ pub trait FToken {
         owner: ActorId,
         approved_account: ActorId,
         amount: u128,
-        permit_id: u128,
+        permit_id: PermitNonce,
+        deadline: u64,
         sign: Signature,
         error: bool,
     );
+    fn permit_domain(&self) -> PermitDomain;
+    fn update_logic_contract(&self, from: u64, ft_logic_code_hash: H256, storage_code_hash: H256);
+    fn migrate_storage_addresses(&self, from: u64);
+    fn ft_logic<'a>(&self, system: &'a System) -> Program<'a>;
+    fn add_minter(&self, from: u64, minter: u64);
+    fn remove_minter(&self, from: u64, minter: u64);
+    fn send_message_and_check_err(&self, from: u64, payload: FTokenAction, error: FTokenError);
     fn send_message_and_check_res(&self, from: u64, payload: FTokenAction, error: bool);
 }
 
-const HARDCODED_ACCOUNT: u64 = 100;
+pub const HARDCODED_ACCOUNT: u64 = 100;
+pub const TOKEN_NAME: &str = "Sharded Token";
+pub const TOKEN_SYMBOL: &str = "SFT";
+pub const TOKEN_DECIMALS: u8 = 18;
+
+pub fn submit_storage_code(system: &System) -> H256 {
+    let code_hash: [u8; 32] = system
+        .submit_code("../target/wasm32-unknown-unknown/debug/ft_storage.opt.wasm")
+        .into();
+    code_hash.into()
+}
+
+pub fn submit_ft_logic_code(system: &System) -> H256 {
+    let code_hash: [u8; 32] = system
+        .submit_code("../target/wasm32-unknown-unknown/debug/ft_logic.opt.wasm")
+        .into();
+    code_hash.into()
+}
 
 impl FToken for Program<'_> {
     fn ftoken(system: &System) -> Program {
+        Self::ftoken_with_config(system, |_| {})
+    }
+
+    fn ftoken_with_config(system: &System, config: impl FnOnce(&mut InitFToken)) -> Program {
         let ftoken = Program::current(system);
-        let storage_code_hash: [u8; 32] = system
-            .submit_code("../target/wasm32-unknown-unknown/debug/ft_storage.opt.wasm")
-            .into();
+        let storage_code_hash = submit_storage_code(system);
+        let ft_logic_code_hash = submit_ft_logic_code(system);
 
-        let ft_logic_code_hash: [u8; 32] = system
-            .submit_code("../target/wasm32-unknown-unknown/debug/ft_logic.opt.wasm")
-            .into();
+        let mut init_config = InitFToken {
+            name: TOKEN_NAME.into(),
+            symbol: TOKEN_SYMBOL.into(),
+            decimals: TOKEN_DECIMALS,
+            storage_code_hash,
+            ft_logic_code_hash,
+            max_supply: None,
+            shard_bits: 4,
+            allowlist: false,
+            signers: vec![],
+            threshold: 0,
+            upgrade_delay: 0,
+        };
+        config(&mut init_config);
 
-        let res = ftoken.send(
-            HARDCODED_ACCOUNT,
-            InitFToken {
-                storage_code_hash: storage_code_hash.into(),
-                ft_logic_code_hash: ft_logic_code_hash.into(),
-            },
-        );
+        let res = ftoken.send(HARDCODED_ACCOUNT, init_config);
         assert!(!res.main_failed());
         ftoken
     }
@@ -142,6 +201,50 @@ impl FToken for Program<'_> {
         );
     }
 
+    fn increase_allowance(
+        &self,
+        transaction_id: u64,
+        from: u64,
+        approved_account: u64,
+        amount: u128,
+        error: bool,
+    ) {
+        let payload = LogicAction::IncreaseAllowance {
+            approved_account: approved_account.into(),
+            amount,
+        };
+        self.send_message_and_check_res(
+            from,
+            FTokenAction::Message {
+                transaction_id,
+                payload,
+            },
+            error,
+        );
+    }
+
+    fn decrease_allowance(
+        &self,
+        transaction_id: u64,
+        from: u64,
+        approved_account: u64,
+        amount: u128,
+        error: bool,
+    ) {
+        let payload = LogicAction::DecreaseAllowance {
+            approved_account: approved_account.into(),
+            amount,
+        };
+        self.send_message_and_check_res(
+            from,
+            FTokenAction::Message {
+                transaction_id,
+                payload,
+            },
+            error,
+        );
+    }
+
     fn permit(
         &self,
         transaction_id: u64,
@@ -149,7 +252,8 @@ impl FToken for Program<'_> {
         owner: ActorId,
         approved_account: ActorId,
         amount: u128,
-        permit_id: u128,
+        permit_id: PermitNonce,
+        deadline: u64,
         sign: Signature,
         error: bool,
     ) {
@@ -158,7 +262,8 @@ impl FToken for Program<'_> {
             approved_account,
             amount,
             permit_id,
-            sign: sign.into(),
+            deadline,
+            sign: MultiSignature::Sr25519(sign.into()),
         };
         self.send_message_and_check_res(
             from,
@@ -186,14 +291,93 @@ impl FToken for Program<'_> {
         assert!(res.contains(&payload));
     }
 
+    fn check_total_supply(&self, expected_total_supply: u128) {
+        let res = self.send(HARDCODED_ACCOUNT, FTokenAction::GetTotalSupply);
+        let payload = Log::builder()
+            .dest(HARDCODED_ACCOUNT)
+            .payload(FTokenEvent::TotalSupply(expected_total_supply));
+        assert!(res.contains(&payload));
+    }
+
+    fn check_allowance(
+        &self,
+        owner: impl Into<ActorId>,
+        spender: impl Into<ActorId>,
+        expected_allowance: u128,
+    ) {
+        let res = self.send(
+            HARDCODED_ACCOUNT,
+            FTokenAction::GetAllowance {
+                owner: owner.into(),
+                spender: spender.into(),
+            },
+        );
+        let payload = Log::builder()
+            .dest(HARDCODED_ACCOUNT)
+            .payload(FTokenEvent::Allowance(expected_allowance));
+        assert!(res.contains(&payload));
+    }
+
+    fn permit_domain(&self) -> PermitDomain {
+        let ftoken_id: [u8; 32] = self
+            .id()
+            .as_ref()
+            .try_into()
+            .expect("Unable to get the program id");
+        PermitDomain {
+            ftoken_id: ftoken_id.into(),
+            version: PERMIT_VERSION,
+        }
+    }
+
+    fn update_logic_contract(&self, from: u64, ft_logic_code_hash: H256, storage_code_hash: H256) {
+        let res = self.send(
+            from,
+            FTokenAction::UpdateLogicContract {
+                ft_logic_code_hash,
+                storage_code_hash,
+            },
+        );
+        assert!(!res.main_failed());
+    }
+
+    fn migrate_storage_addresses(&self, from: u64) {
+        let res = self.send(from, FTokenAction::MigrateStorageAddresses);
+        assert!(res.contains(&(from, FTokenEvent::Ok.encode())));
+    }
+
+    fn ft_logic<'a>(&self, system: &'a System) -> Program<'a> {
+        let state: FTokenState = self.read_state().expect("Unable to read `FTokenState`");
+        system.get_program(<[u8; 32]>::from(state.ft_logic_id))
+    }
+
+    fn add_minter(&self, from: u64, minter: u64) {
+        let res = self.send(from, FTokenAction::AddMinter(minter.into()));
+        assert!(res.contains(&(from, FTokenEvent::Ok.encode())));
+    }
+
+    fn remove_minter(&self, from: u64, minter: u64) {
+        let res = self.send(from, FTokenAction::RemoveMinter(minter.into()));
+        assert!(res.contains(&(from, FTokenEvent::Ok.encode())));
+    }
+
+    fn send_message_and_check_err(&self, from: u64, payload: FTokenAction, error: FTokenError) {
+        let res = self.send(from, payload);
+        assert!(res.contains(&(from, FTokenEvent::Err(error).encode())));
+    }
+
     fn send_message_and_check_res(&self, from: u64, payload: FTokenAction, error: bool) {
         let res = self.send(from, payload);
-        let reply = if error {
-            FTokenEvent::Err.encode()
+        if error {
+            assert!(res.log().iter().any(|log| {
+                log.destination() == from.into()
+                    && matches!(
+                        FTokenEvent::decode(&mut log.payload()),
+                        Ok(FTokenEvent::Err(_))
+                    )
+            }));
         } else {
-            FTokenEvent::Ok.encode()
-        };
-
-        assert!(res.contains(&(from, reply)));
+            assert!(res.contains(&(from, FTokenEvent::Ok.encode())));
+        }
     }
 }
@@ -1,4 +1,5 @@
 #![no_std]
+pub use ft_main_io::FTokenError;
 use gmeta::{InOut, Metadata};
 use gstd::{prelude::*, ActorId};
 use primitive_types::H256;
@@ -17,13 +18,17 @@ impl Metadata for FTStorageMetadata {
 #[derive(Default, Encode, Decode, TypeInfo, Debug)]
 pub struct FTStorageState {
     pub ft_logic_id: ActorId,
-    pub transaction_status: Vec<(H256, bool)>,
+    pub transaction_status: Vec<(H256, Result<(), FTokenError>)>,
     pub balances: Vec<(ActorId, u128)>,
     pub approvals: Vec<(ActorId, Vec<(ActorId, u128)>)>,
     pub permits: Vec<(ActorId, u128)>,
+    pub used_nonces: Vec<(ActorId, Vec<H256>)>,
+    pub permit_nonces: Vec<(ActorId, Vec<u128>)>,
+    pub exported_up_to: Option<ActorId>,
+    pub locked: bool,
 }
 
-#[derive(Encode, Decode, Debug, Copy, Clone, TypeInfo)]
+#[derive(Encode, Decode, Debug, Clone, TypeInfo)]
 pub enum FTStorageAction {
     GetBalance(ActorId),
     GetPermitId(ActorId),
@@ -43,12 +48,37 @@ pub enum FTStorageAction {
         account: ActorId,
         amount: u128,
     },
+    /// Increases the balances of all the accounts.
+    IncreaseBalances {
+        transaction_hash: H256,
+        accounts: Vec<(ActorId, u128)>,
+    },
+    /// Decreases the balances of all the accounts or none of them
+    /// if any balance is insufficient, it compensates `IncreaseBalances`.
+    DecreaseBalances {
+        transaction_hash: H256,
+        accounts: Vec<(ActorId, u128)>,
+    },
+    /// Sets the amount the `account` is allowed to transfer on behalf of the `msg_source`.
     Approve {
         transaction_hash: H256,
         msg_source: ActorId,
         account: ActorId,
         amount: u128,
     },
+    IncreaseAllowance {
+        transaction_hash: H256,
+        msg_source: ActorId,
+        account: ActorId,
+        amount: u128,
+    },
+    /// Fails with `FTokenError::InsufficientAllowance` if the allowance is less than `amount`.
+    DecreaseAllowance {
+        transaction_hash: H256,
+        msg_source: ActorId,
+        account: ActorId,
+        amount: u128,
+    },
     Transfer {
         transaction_hash: H256,
         msg_source: ActorId,
@@ -56,12 +86,57 @@ pub enum FTStorageAction {
         recipient: ActorId,
         amount: u128,
     },
+    UpdateLogicContract(ActorId),
+    /// Returns the data of the accounts after the given one sorted by their addresses
+    /// and locks these accounts. The actions that change the balances, approvals or permit ids
+    /// of the locked accounts are rejected. Once there are less than `limit` accounts left,
+    /// the whole storage is locked.
+    ExportAccounts {
+        after: Option<ActorId>,
+        limit: u32,
+    },
+    ImportAccounts {
+        transaction_hash: H256,
+        accounts: Vec<AccountData>,
+    },
+    GetAllowance {
+        owner: ActorId,
+        spender: ActorId,
+    },
+    /// Marks the unordered nonce of the account's permit as used,
+    /// fails with `FTokenError::NonceUsed` if it has already been used.
+    UsePermitNonce {
+        transaction_hash: H256,
+        account: ActorId,
+        nonce: u128,
+    },
+    /// Marks the nonce of the account's transfer authorization as used,
+    /// fails with `FTokenError::NonceUsed` if it has already been used.
+    UseNonce {
+        transaction_hash: H256,
+        account: ActorId,
+        nonce: H256,
+    },
 }
 
 #[derive(Encode, Decode, Clone, Debug, TypeInfo)]
 pub enum FTStorageEvent {
     Ok,
-    Err,
+    Err(FTokenError),
     Balance(u128),
     PermitId(u128),
+    Accounts(Vec<AccountData>),
+    Allowance(u128),
+}
+
+/// Everything the storage keeps about an account,
+/// used to move the account from one storage to another.
+#[derive(Encode, Decode, Clone, Debug, TypeInfo)]
+pub struct AccountData {
+    pub account: ActorId,
+    pub balance: u128,
+    pub approvals: Vec<(ActorId, u128)>,
+    pub permit_id: u128,
+    pub used_nonces: Vec<H256>,
+    pub permit_nonces: Vec<u128>,
 }
@@ -1,16 +1,24 @@
 #![no_std]
+use core::ops::Bound::{Excluded, Unbounded};
 use ft_storage_io::*;
 use gstd::{msg, prelude::*, ActorId};
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use primitive_types::H256;
 
 #[derive(Default)]
 struct FTStorage {
     ft_logic_id: ActorId,
-    transaction_status: HashMap<H256, bool>,
+    transaction_status: HashMap<H256, Result<(), FTokenError>>,
     balances: HashMap<ActorId, u128>,
     approvals: HashMap<ActorId, HashMap<ActorId, u128>>,
     permits: HashMap<ActorId, u128>,
+    used_nonces: HashMap<ActorId, HashSet<H256>>,
+    permit_nonces: HashMap<ActorId, HashSet<u128>>,
+    /// The accounts that have any data in the storage, ordered for the export.
+    accounts: BTreeSet<ActorId>,
+    /// The last account exported to other storages, the accounts up to it are locked.
+    exported_up_to: Option<ActorId>,
+    locked: bool,
 }
 
 static mut FT_STORAGE: Option<FTStorage> = None;
@@ -32,14 +40,19 @@ impl FTStorage {
         // check transaction status
         if let Some(status) = self.transaction_status.get(&transaction_hash) {
             match status {
-                true => reply_ok(),
-                false => reply_err(),
+                Ok(()) => reply_ok(),
+                Err(error) => reply_err(*error),
             };
             return;
         }
 
+        if self.is_locked([account]) {
+            reply_err(FTokenError::StorageLocked);
+            return;
+        }
+
         if self.permits.get(account).unwrap_or(&0) != signed_permit_id {
-            reply_err();
+            reply_err(FTokenError::WrongPermitId);
             return;
         }
 
@@ -47,35 +60,109 @@ impl FTStorage {
             .entry(*account)
             .and_modify(|id| *id += 1)
             .or_insert(1);
+        self.accounts.insert(*account);
         reply_ok();
     }
 
+    fn use_permit_nonce(&mut self, transaction_hash: H256, account: &ActorId, nonce: u128) {
+        self.assert_ft_contract();
+
+        // check transaction status
+        if let Some(status) = self.transaction_status.get(&transaction_hash) {
+            match status {
+                Ok(()) => reply_ok(),
+                Err(error) => reply_err(*error),
+            };
+            return;
+        }
+
+        if self.is_locked([account]) {
+            reply_err(FTokenError::StorageLocked);
+            return;
+        }
+
+        if self
+            .permit_nonces
+            .entry(*account)
+            .or_default()
+            .insert(nonce)
+        {
+            self.accounts.insert(*account);
+            self.transaction_status.insert(transaction_hash, Ok(()));
+            reply_ok();
+        } else {
+            self.transaction_status
+                .insert(transaction_hash, Err(FTokenError::NonceUsed));
+            reply_err(FTokenError::NonceUsed);
+        }
+    }
+
+    fn use_nonce(&mut self, transaction_hash: H256, account: &ActorId, nonce: H256) {
+        self.assert_ft_contract();
+
+        // check transaction status
+        if let Some(status) = self.transaction_status.get(&transaction_hash) {
+            match status {
+                Ok(()) => reply_ok(),
+                Err(error) => reply_err(*error),
+            };
+            return;
+        }
+
+        if self.is_locked([account]) {
+            reply_err(FTokenError::StorageLocked);
+            return;
+        }
+
+        if self.used_nonces.entry(*account).or_default().insert(nonce) {
+            self.accounts.insert(*account);
+            self.transaction_status.insert(transaction_hash, Ok(()));
+            reply_ok();
+        } else {
+            self.transaction_status
+                .insert(transaction_hash, Err(FTokenError::NonceUsed));
+            reply_err(FTokenError::NonceUsed);
+        }
+    }
+
     fn get_balance(&self, account: &ActorId) {
         let balance = self.balances.get(account).unwrap_or(&0);
         msg::reply(FTStorageEvent::Balance(*balance), 0).expect("");
     }
 
-    fn decrease(&mut self, msg_source: &ActorId, sender: &ActorId, amount: u128) -> bool {
-        if let Some(balance) = self.balances.get_mut(sender) {
-            if *balance >= amount {
-                if msg_source == sender {
-                    *balance -= amount;
-                    return true;
-                } else if let Some(allowed_amount) = self
-                    .approvals
-                    .get_mut(sender)
-                    .and_then(|m| m.get_mut(msg_source))
-                {
-                    if *allowed_amount >= amount {
-                        *balance -= amount;
-                        *allowed_amount -= amount;
-                        return true;
-                    }
-                }
-            }
+    fn get_allowance(&self, owner: &ActorId, spender: &ActorId) {
+        let allowance = self
+            .approvals
+            .get(owner)
+            .and_then(|approvals| approvals.get(spender))
+            .unwrap_or(&0);
+        msg::reply(FTStorageEvent::Allowance(*allowance), 0).expect("");
+    }
+
+    fn decrease(
+        &mut self,
+        msg_source: &ActorId,
+        sender: &ActorId,
+        amount: u128,
+    ) -> Result<(), FTokenError> {
+        let balance = self
+            .balances
+            .get_mut(sender)
+            .filter(|balance| **balance >= amount)
+            .ok_or(FTokenError::InsufficientBalance)?;
+        if msg_source != sender {
+            let allowed_amount = self
+                .approvals
+                .get_mut(sender)
+                .and_then(|m| m.get_mut(msg_source))
+                .filter(|allowed_amount| **allowed_amount >= amount)
+                .ok_or(FTokenError::InsufficientAllowance)?;
+            *allowed_amount -= amount;
         }
-        false
+        *balance -= amount;
+        Ok(())
     }
+
     fn transfer(
         &mut self,
         transaction_hash: H256,
@@ -89,25 +176,31 @@ impl FTStorage {
         // check transaction status
         if let Some(status) = self.transaction_status.get(&transaction_hash) {
             match status {
-                true => reply_ok(),
-                false => reply_err(),
+                Ok(()) => reply_ok(),
+                Err(error) => reply_err(*error),
             };
             return;
         }
 
+        if self.is_locked([sender, recipient]) {
+            reply_err(FTokenError::StorageLocked);
+            return;
+        }
+
         match self.decrease(msg_source, sender, amount) {
-            true => {
+            Ok(()) => {
                 self.balances
                     .entry(*recipient)
                     .and_modify(|balance| *balance = (*balance).saturating_add(amount))
                     .or_insert(amount);
+                self.accounts.insert(*recipient);
 
-                self.transaction_status.insert(transaction_hash, true);
+                self.transaction_status.insert(transaction_hash, Ok(()));
                 reply_ok();
             }
-            false => {
-                self.transaction_status.insert(transaction_hash, false);
-                reply_err();
+            Err(error) => {
+                self.transaction_status.insert(transaction_hash, Err(error));
+                reply_err(error);
             }
         }
     }
@@ -118,19 +211,96 @@ impl FTStorage {
         // check transaction status
         if let Some(status) = self.transaction_status.get(&transaction_hash) {
             match status {
-                true => reply_ok(),
-                false => reply_err(),
+                Ok(()) => reply_ok(),
+                Err(error) => reply_err(*error),
             };
             return;
         }
 
+        if self.is_locked([account]) {
+            reply_err(FTokenError::StorageLocked);
+            return;
+        }
+
         // increase balance
         self.balances
             .entry(*account)
             .and_modify(|balance| *balance = (*balance).saturating_add(amount))
             .or_insert(amount);
+        self.accounts.insert(*account);
+
+        self.transaction_status.insert(transaction_hash, Ok(()));
+        reply_ok();
+    }
+
+    fn increase_balances(&mut self, transaction_hash: H256, accounts: &[(ActorId, u128)]) {
+        self.assert_ft_contract();
+
+        // check transaction status
+        if let Some(status) = self.transaction_status.get(&transaction_hash) {
+            match status {
+                Ok(()) => reply_ok(),
+                Err(error) => reply_err(*error),
+            };
+            return;
+        }
+
+        if self.is_locked(accounts.iter().map(|(account, _)| account)) {
+            reply_err(FTokenError::StorageLocked);
+            return;
+        }
+
+        for (account, amount) in accounts {
+            self.balances
+                .entry(*account)
+                .and_modify(|balance| *balance = (*balance).saturating_add(*amount))
+                .or_insert(*amount);
+            self.accounts.insert(*account);
+        }
+
+        self.transaction_status.insert(transaction_hash, Ok(()));
+        reply_ok();
+    }
+
+    fn decrease_balances(&mut self, transaction_hash: H256, accounts: &[(ActorId, u128)]) {
+        self.assert_ft_contract();
+
+        // check transaction status
+        if let Some(status) = self.transaction_status.get(&transaction_hash) {
+            match status {
+                Ok(()) => reply_ok(),
+                Err(error) => reply_err(*error),
+            };
+            return;
+        }
+
+        if self.is_locked(accounts.iter().map(|(account, _)| account)) {
+            reply_err(FTokenError::StorageLocked);
+            return;
+        }
+
+        // the same account may occur several times
+        let mut amounts: HashMap<ActorId, u128> = HashMap::new();
+        for (account, amount) in accounts {
+            let total = amounts.entry(*account).or_default();
+            *total = total.saturating_add(*amount);
+        }
+        let sufficient = amounts.iter().all(|(account, amount)| {
+            self.balances.get(account).copied().unwrap_or_default() >= *amount
+        });
+        if !sufficient {
+            self.transaction_status
+                .insert(transaction_hash, Err(FTokenError::InsufficientBalance));
+            reply_err(FTokenError::InsufficientBalance);
+            return;
+        }
+        for (account, amount) in amounts {
+            if let Some(balance) = self.balances.get_mut(&account) {
+                *balance -= amount;
+            }
+        }
 
-        self.transaction_status.insert(transaction_hash, true);
+        self.transaction_status.insert(transaction_hash, Ok(()));
         reply_ok();
     }
 
@@ -145,55 +315,191 @@ impl FTStorage {
         // check transaction status
         if let Some(status) = self.transaction_status.get(&transaction_hash) {
             match status {
-                true => reply_ok(),
-                false => reply_err(),
+                Ok(()) => reply_ok(),
+                Err(error) => reply_err(*error),
             };
             return;
         }
 
+        if self.is_locked([account]) {
+            reply_err(FTokenError::StorageLocked);
+            return;
+        }
+
         // decrease balance
         match self.decrease(msg_source, account, amount) {
-            true => {
-                self.transaction_status.insert(transaction_hash, true);
+            Ok(()) => {
+                self.transaction_status.insert(transaction_hash, Ok(()));
                 reply_ok();
             }
-            false => {
-                self.transaction_status.insert(transaction_hash, false);
-                reply_err();
+            Err(error) => {
+                self.transaction_status.insert(transaction_hash, Err(error));
+                reply_err(error);
             }
         }
     }
 
-    fn approve(
+    /// Changes the amount the `spender` is allowed to transfer on behalf of the `owner`
+    /// to the value returned by `change` for the current allowance.
+    fn change_allowance(
         &mut self,
         transaction_hash: H256,
-        msg_source: &ActorId,
-        account: &ActorId,
-        amount: u128,
+        owner: &ActorId,
+        spender: &ActorId,
+        change: impl FnOnce(u128) -> Result<u128, FTokenError>,
     ) {
         self.assert_ft_contract();
 
         // check transaction status
         if let Some(status) = self.transaction_status.get(&transaction_hash) {
             match status {
-                true => reply_ok(),
-                false => reply_err(),
+                Ok(()) => reply_ok(),
+                Err(error) => reply_err(*error),
             };
             return;
         }
 
-        self.approvals
-            .entry(*msg_source)
-            .and_modify(|accounts| {
-                accounts
-                    .entry(*account)
-                    .and_modify(|allowed_amount| {
-                        *allowed_amount = (*allowed_amount).saturating_add(amount)
+        if self.is_locked([owner]) {
+            reply_err(FTokenError::StorageLocked);
+            return;
+        }
+
+        let allowance = self
+            .approvals
+            .get(owner)
+            .and_then(|approvals| approvals.get(spender))
+            .copied()
+            .unwrap_or_default();
+        match change(allowance) {
+            Ok(allowance) => {
+                if allowance == 0 {
+                    if let Some(approvals) = self.approvals.get_mut(owner) {
+                        approvals.remove(spender);
+                    }
+                } else {
+                    self.approvals
+                        .entry(*owner)
+                        .or_default()
+                        .insert(*spender, allowance);
+                    self.accounts.insert(*owner);
+                }
+                self.transaction_status.insert(transaction_hash, Ok(()));
+                reply_ok();
+            }
+            Err(error) => {
+                self.transaction_status.insert(transaction_hash, Err(error));
+                reply_err(error);
+            }
+        }
+    }
+
+    /// Hands the storage over to a new logic contract during the logic upgrade.
+    /// Repeating the call with the same logic contract is a no-op, so the migration can be retried.
+    fn update_logic_contract(&mut self, ft_logic_id: &ActorId) {
+        if self.ft_logic_id != *ft_logic_id {
+            self.assert_ft_contract();
+            self.ft_logic_id = *ft_logic_id;
+        }
+        reply_ok();
+    }
+
+    /// Checks whether any of the accounts has already been exported to another storage
+    /// and can't be changed here anymore.
+    fn is_locked<'a>(&self, accounts: impl IntoIterator<Item = &'a ActorId>) -> bool {
+        self.locked
+            || accounts
+                .into_iter()
+                .any(|account| Some(*account) <= self.exported_up_to)
+    }
+
+    /// Replies with a chunk of the accounts sorted by their addresses
+    /// and locks them, so the accounts can be moved to other storages
+    /// while the storage is split or upgraded. The other accounts can still be changed
+    /// until the last chunk is exported, then the whole storage is locked.
+    ///
+    /// Arguments:
+    /// * `after`: the last account of the previous chunk;
+    /// * `limit`: the maximum number of accounts in the reply.
+    fn export_accounts(&mut self, after: Option<ActorId>, limit: u32) {
+        self.assert_ft_contract();
+
+        let from = after.map_or(Unbounded, Excluded);
+        let accounts: Vec<ActorId> = self
+            .accounts
+            .range((from, Unbounded))
+            .take(limit as usize)
+            .copied()
+            .collect();
+        if accounts.len() < limit as usize {
+            self.locked = true;
+        }
+        self.exported_up_to = self.exported_up_to.max(accounts.last().copied());
+
+        let accounts = accounts
+            .into_iter()
+            .map(|account| AccountData {
+                account,
+                balance: self.balances.get(&account).copied().unwrap_or_default(),
+                approvals: self
+                    .approvals
+                    .get(&account)
+                    .map(|approvals| {
+                        approvals
+                            .iter()
+                            .map(|(key, value)| (*key, *value))
+                            .collect()
                     })
-                    .or_insert_with(|| amount);
+                    .unwrap_or_default(),
+                permit_id: self.permits.get(&account).copied().unwrap_or_default(),
+                used_nonces: self
+                    .used_nonces
+                    .get(&account)
+                    .map(|nonces| nonces.iter().copied().collect())
+                    .unwrap_or_default(),
+                permit_nonces: self
+                    .permit_nonces
+                    .get(&account)
+                    .map(|nonces| nonces.iter().copied().collect())
+                    .unwrap_or_default(),
             })
-            .or_insert_with(|| [(*account, amount)].into());
+            .collect();
+        msg::reply(FTStorageEvent::Accounts(accounts), 0)
+            .expect("error in sending a reply `FTStorageEvent::Accounts");
+    }
 
+    fn import_accounts(&mut self, transaction_hash: H256, accounts: Vec<AccountData>) {
+        self.assert_ft_contract();
+
+        // check transaction status
+        if self.transaction_status.contains_key(&transaction_hash) {
+            reply_ok();
+            return;
+        }
+
+        for account_data in accounts {
+            let account = account_data.account;
+            self.accounts.insert(account);
+            if account_data.balance > 0 {
+                self.balances.insert(account, account_data.balance);
+            }
+            if !account_data.approvals.is_empty() {
+                self.approvals
+                    .insert(account, account_data.approvals.into_iter().collect());
+            }
+            if account_data.permit_id > 0 {
+                self.permits.insert(account, account_data.permit_id);
+            }
+            if !account_data.used_nonces.is_empty() {
+                self.used_nonces
+                    .insert(account, account_data.used_nonces.into_iter().collect());
+            }
+            if !account_data.permit_nonces.is_empty() {
+                self.permit_nonces
+                    .insert(account, account_data.permit_nonces.into_iter().collect());
+            }
+        }
+
+        self.transaction_status.insert(transaction_hash, Ok(()));
         reply_ok();
     }
 
@@ -212,6 +518,17 @@ unsafe extern "C" fn handle() {
     match action {
         FTStorageAction::GetBalance(account) => storage.get_balance(&account),
         FTStorageAction::GetPermitId(account) => storage.get_permit_id(&account),
+        FTStorageAction::GetAllowance { owner, spender } => storage.get_allowance(&owner, &spender),
+        FTStorageAction::UsePermitNonce {
+            transaction_hash,
+            account,
+            nonce,
+        } => storage.use_permit_nonce(transaction_hash, &account, nonce),
+        FTStorageAction::UseNonce {
+            transaction_hash,
+            account,
+            nonce,
+        } => storage.use_nonce(transaction_hash, &account, nonce),
         FTStorageAction::IncrementPermitId {
             transaction_hash,
             account,
@@ -228,12 +545,38 @@ unsafe extern "C" fn handle() {
             account,
             amount,
         } => storage.decrease_balance(transaction_hash, &msg_source, &account, amount),
+        FTStorageAction::IncreaseBalances {
+            transaction_hash,
+            accounts,
+        } => storage.increase_balances(transaction_hash, &accounts),
+        FTStorageAction::DecreaseBalances {
+            transaction_hash,
+            accounts,
+        } => storage.decrease_balances(transaction_hash, &accounts),
         FTStorageAction::Approve {
             transaction_hash,
             msg_source,
             account,
             amount,
-        } => storage.approve(transaction_hash, &msg_source, &account, amount),
+        } => storage.change_allowance(transaction_hash, &msg_source, &account, |_| Ok(amount)),
+        FTStorageAction::IncreaseAllowance {
+            transaction_hash,
+            msg_source,
+            account,
+            amount,
+        } => storage.change_allowance(transaction_hash, &msg_source, &account, |allowance| {
+            Ok(allowance.saturating_add(amount))
+        }),
+        FTStorageAction::DecreaseAllowance {
+            transaction_hash,
+            msg_source,
+            account,
+            amount,
+        } => storage.change_allowance(transaction_hash, &msg_source, &account, |allowance| {
+            allowance
+                .checked_sub(amount)
+                .ok_or(FTokenError::InsufficientAllowance)
+        }),
         FTStorageAction::Transfer {
             transaction_hash,
             msg_source,
@@ -241,6 +584,14 @@ unsafe extern "C" fn handle() {
             recipient,
             amount,
         } => storage.transfer(transaction_hash, &msg_source, &sender, &recipient, amount),
+        FTStorageAction::UpdateLogicContract(ft_logic_id) => {
+            storage.update_logic_contract(&ft_logic_id)
+        }
+        FTStorageAction::ExportAccounts { after, limit } => storage.export_accounts(after, limit),
+        FTStorageAction::ImportAccounts {
+            transaction_hash,
+            accounts,
+        } => storage.import_accounts(transaction_hash, accounts),
     }
 }
 
@@ -257,8 +608,9 @@ fn reply_ok() {
     msg::reply(FTStorageEvent::Ok, 0).expect("error in sending a reply `FTStorageEvent::Ok");
 }
 
-fn reply_err() {
-    msg::reply(FTStorageEvent::Err, 0).expect("error in sending a reply `FTStorageEvent::Err");
+fn reply_err(error: FTokenError) {
+    msg::reply(FTStorageEvent::Err(error), 0)
+        .expect("error in sending a reply `FTStorageEvent::Err");
 }
 
 #[no_mangle]
@@ -291,6 +643,18 @@ extern "C" fn state() {
             .iter()
             .map(|(key, value)| (*key, *value))
             .collect(),
+        used_nonces: storage
+            .used_nonces
+            .iter()
+            .map(|(key, value)| (*key, value.iter().copied().collect()))
+            .collect(),
+        permit_nonces: storage
+            .permit_nonces
+            .iter()
+            .map(|(key, value)| (*key, value.iter().copied().collect()))
+            .collect(),
+        exported_up_to: storage.exported_up_to,
+        locked: storage.locked,
     };
     msg::reply(storage_state, 0).expect("Failed to share state");
 }